@@ -5,6 +5,8 @@ use nbody_sim::nbody::*;
 use std::time::Duration;
 use nbody_sim::nbody::shader_types::nbody::SimulationParams;
 
+const SEED: u64 = 42;
+
 fn configure_criterion() -> Criterion {
     Criterion::default()
         .sample_size(20)
@@ -17,7 +19,7 @@ fn benchmark_scaling_comparison(c: &mut Criterion) {
     group.plot_config(PlotConfiguration::default().summary_scale(AxisScale::Logarithmic));
 
     for n in [1000, 2500, 5_000, 10_000, 25000, 50_000, 100_000].iter() {
-        let bodies = utils::generate_random_bodies(*n, 100.0);
+        let bodies = utils::generate_random_bodies_seeded(*n, 100.0, SEED);
         let params = SimulationParams::default();
 
         group.bench_with_input(BenchmarkId::new("CPU Single", n), n, |b, _| {
@@ -61,7 +63,7 @@ fn benchmark_scaling_comparison(c: &mut Criterion) {
         });
 
         let mut simd_aligned_bodies = SimdAlignedNBodyCore::new(bodies.clone());
-        group.bench_with_input(BenchmarkId::new("SIMD Alligned", n), n, |b, &s| {
+        group.bench_with_input(BenchmarkId::new("SIMD Alligned", n), n, |b, _| {
             b.iter(|| {
                 simd_aligned_bodies.step(black_box(1));
             });
@@ -77,7 +79,7 @@ fn benchmark_step_scaling(c: &mut Criterion) {
     group.plot_config(PlotConfiguration::default().summary_scale(AxisScale::Logarithmic));
 
     const NUM_BODIES: usize = 10_000;
-    let bodies = utils::generate_random_bodies(NUM_BODIES, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(NUM_BODIES, 100.0, SEED);
     let params = SimulationParams::default();
 
     for steps in [1, 10, 50, 100, 250, 500].iter() {
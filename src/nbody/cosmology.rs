@@ -0,0 +1,210 @@
+//! Comoving integration on a Friedmann background, with the scale factor `a` as time variable.
+//!
+//! Positions are comoving `x = r / a` and `Body::velocity` holds the canonical momentum per
+//! unit mass `p = a² dx/dt`; `to_peculiar` and `from_peculiar` convert it to and from the
+//! peculiar velocity `a dx/dt`. With the comoving force `g` (the kernels' sum with comoving
+//! separations and `g_constant`) the equations of motion are `dp/dt = g / a` and
+//! `dx/dt = p / a²`, so a step from `a₀` to `a₁` kicks by `K = ∫ dt / a` and drifts by
+//! `D = ∫ dt / a²`. `Comoving` runs any backend with `dt = D` and `g_constant · K / D`,
+//! which makes its kick `g K`; external fields are not rescaled. Each step is the backend's
+//! kick followed by its drift over the whole interval, i.e. symplectic Euler, so the result
+//! is first order in `da`; keep `da` small against `a`.
+//!
+//! Bodies must live in a periodic box with `ewald`, whose sum already subtracts the mean
+//! density. Open boundaries would also need the pull of the homogeneous background, which
+//! isn't modelled. 1PN and rotating frames do not apply either; `check_params` enforces all
+//! three.
+use crate::nbody::periodic;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::simulation_trait::Simulation;
+
+/// Simpson intervals per factor; the integrands are smooth in `a`.
+const INTERVALS: usize = 64;
+
+/// Background with matter, a cosmological constant and curvature `1 - Ω_m - Ω_Λ`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cosmology {
+    pub omega_m: f64,
+    pub omega_lambda: f64,
+    /// Hubble constant in simulation units of inverse time.
+    pub h0: f64,
+}
+
+impl Cosmology {
+    pub fn new(omega_m: f64, omega_lambda: f64, h0: f64) -> Self {
+        Self { omega_m, omega_lambda, h0 }
+    }
+
+    /// Flat, matter only.
+    pub fn einstein_de_sitter(h0: f64) -> Self {
+        Self::new(1.0, 0.0, h0)
+    }
+
+    #[inline]
+    pub fn omega_k(&self) -> f64 {
+        1.0 - self.omega_m - self.omega_lambda
+    }
+
+    /// Hubble rate `H(a) = H0 sqrt(Ω_m / a³ + Ω_k / a² + Ω_Λ)`.
+    pub fn hubble(&self, a: f64) -> f64 {
+        self.h0 * (self.omega_m / (a * a * a) + self.omega_k() / (a * a) + self.omega_lambda).sqrt()
+    }
+
+    /// Time since the big bang at scale factor `a`.
+    pub fn cosmic_time(&self, a: f64) -> f64 {
+        // t = ∫ da / (a H) = ∫ 2 s ds / (s² H(s²)) with a = s², which is regular at 0
+        integrate(|s| if s == 0.0 { 0.0 } else { 2.0 / (s * self.hubble(s * s)) }, 0.0, a.sqrt())
+    }
+
+    /// `∫ dt / a` from `a0` to `a1`.
+    pub fn kick_factor(&self, a0: f64, a1: f64) -> f64 {
+        integrate(|a| 1.0 / (a * a * self.hubble(a)), a0, a1)
+    }
+
+    /// `∫ dt / a²` from `a0` to `a1`.
+    pub fn drift_factor(&self, a0: f64, a1: f64) -> f64 {
+        integrate(|a| 1.0 / (a * a * a * self.hubble(a)), a0, a1)
+    }
+}
+
+fn integrate(f: impl Fn(f64) -> f64, from: f64, to: f64) -> f64 {
+    let h = (to - from) / INTERVALS as f64;
+    let inner: f64 = (1..INTERVALS).map(|i| f(from + i as f64 * h) * if i % 2 == 1 { 4.0 } else { 2.0 }).sum();
+    (f(from) + inner + f(to)) * h / 3.0
+}
+
+/// Panics for parameters the comoving equations don't cover.
+pub fn check_params(params: &SimulationParams) {
+    assert!(params.speed_of_light == 0.0, "Comoving integration is Newtonian, disable 1PN");
+    assert!(params.omega == 0.0, "Comoving integration needs an inertial frame");
+    assert!(periodic::uses_ewald(params), "Comoving integration needs a periodic box with Ewald summation");
+}
+
+#[inline]
+pub fn redshift(a: f64) -> f64 {
+    1.0 / a - 1.0
+}
+
+/// Bodies with their canonical momenta replaced by peculiar velocities `p / a`.
+pub fn to_peculiar(bodies: &[Body], a: f64) -> Vec<Body> {
+    let a = a as f32;
+    bodies.iter().map(|body| Body { velocity: body.velocity.map(|p| p / a), ..*body }).collect()
+}
+
+/// Bodies with peculiar velocities replaced by canonical momenta `a v`.
+pub fn from_peculiar(bodies: &[Body], a: f64) -> Vec<Body> {
+    let a = a as f32;
+    bodies.iter().map(|body| Body { velocity: body.velocity.map(|v| v * a), ..*body }).collect()
+}
+
+/// Physical positions `a x` and velocities `H a x + p / a`, including the Hubble flow.
+pub fn to_physical(bodies: &[Body], cosmology: &Cosmology, a: f64) -> Vec<Body> {
+    let hubble = (cosmology.hubble(a) * a) as f32;
+    let a = a as f32;
+    bodies
+        .iter()
+        .map(|body| Body {
+            position: body.position.map(|x| x * a),
+            velocity: [0, 1].map(|k| hubble * body.position[k] + body.velocity[k] / a),
+            ..*body
+        })
+        .collect()
+}
+
+/// Wraps any `Simulation` and steps it in equal increments `da` of the scale factor.
+///
+/// `dt` of the wrapped parameters is ignored; `get_params` returns them as set, the wrapped
+/// backend sees the per-step `dt` and `g_constant`. Its `time()` therefore sums drift
+/// factors rather than cosmic time, and trajectories set on it are evaluated at that sum.
+pub struct Comoving<S: Simulation> {
+    inner: S,
+    cosmology: Cosmology,
+    params: SimulationParams,
+    a: f64,
+    da: f64,
+}
+
+impl<S: Simulation> Comoving<S> {
+    pub fn new(inner: S, cosmology: Cosmology, a_start: f64, da: f64) -> Self {
+        assert!(a_start > 0.0 && da > 0.0, "Scale factor and its step must be positive");
+        let params = *inner.get_params();
+        check_params(&params);
+        Self { inner, cosmology, params, a: a_start, da }
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.a
+    }
+
+    #[inline]
+    pub fn redshift(&self) -> f64 {
+        redshift(self.a)
+    }
+
+    #[inline]
+    pub fn cosmology(&self) -> &Cosmology {
+        &self.cosmology
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Steps until the scale factor reaches `a_end`, shortening the last step to land on it.
+    pub fn run_to(&mut self, a_end: f64) {
+        while self.a < a_end {
+            let a1 = (self.a + self.da).min(a_end);
+            self.advance(a1);
+        }
+    }
+
+    fn advance(&mut self, a1: f64) {
+        let kick = self.cosmology.kick_factor(self.a, a1);
+        let drift = self.cosmology.drift_factor(self.a, a1);
+        self.inner.set_params(SimulationParams {
+            dt: drift as f32,
+            g_constant: (self.params.g_constant as f64 * kick / drift) as f32,
+            ..self.params
+        });
+        self.inner.step(1);
+        self.a = a1;
+    }
+}
+
+impl<S: Simulation> Simulation for Comoving<S> {
+    fn step(&mut self, steps: usize) {
+        for _ in 0..steps {
+            self.advance(self.a + self.da);
+        }
+    }
+
+    fn get_bodies(&self) -> Vec<Body> {
+        self.inner.get_bodies()
+    }
+
+    fn set_bodies(&mut self, bodies: Vec<Body>) {
+        self.inner.set_bodies(bodies);
+    }
+
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        self.inner.retain_bodies(kept);
+    }
+
+    fn get_params(&self) -> &SimulationParams {
+        &self.params
+    }
+
+    fn set_params(&mut self, simulation_params: SimulationParams) {
+        check_params(&simulation_params);
+        self.params = simulation_params;
+    }
+}
@@ -1,52 +1,87 @@
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics;
+use crate::nbody::periodic;
+use crate::nbody::rotating_frame;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::species::SpeciesTable;
 
 #[inline]
 pub fn compute_body_update(
     index: usize,
     all_bodies: &[Body],
+    massive: &[usize],
     params: &SimulationParams,
+    external_field: Option<&dyn ExternalField>,
+    post_newtonian: Option<&[[f32; 2]]>,
+    species: Option<&SpeciesTable>,
 ) -> Body {
-    let n = all_bodies.len();
     let current = &all_bodies[index];
-    let mut force = [0.0f32; 2];
+    if let Some(driven) = kinematics::driven_update(current, params) {
+        return driven;
+    }
+    let mut acceleration = [0.0f32; 2];
+    let is_periodic = periodic::is_periodic(params);
+    let uses_ewald = periodic::uses_ewald(params);
 
-    // Calculate force from all other bodies
-    for j in 0..n {
+    // Only bodies with mass pull; test particles feel the others but exert nothing
+    for &j in massive {
         if index == j {
             continue;
         }
-
         let other = &all_bodies[j];
-        let r_vec = [
+        let (coupling, epsilon) = species.map_or((1.0, params.epsilon), |table| table.pair(current, other));
+        if coupling == 0.0 {
+            continue;
+        }
+        let strength = coupling * params.g_constant * other.mass;
+
+        let mut r_vec = [
             other.position[0] - current.position[0],
             other.position[1] - current.position[1],
         ];
 
-        let r_squared = (r_vec[0].powi(2) + r_vec[1].powi(2)).max(params.epsilon);
+        if uses_ewald {
+            let a = periodic::ewald_acceleration(r_vec, params.box_size, epsilon);
+            acceleration[0] += strength * a[0];
+            acceleration[1] += strength * a[1];
+            continue;
+        }
+        if is_periodic {
+            r_vec = r_vec.map(|d| periodic::minimum_image(d, params.box_size));
+        }
+
+        let r_squared = (r_vec[0].powi(2) + r_vec[1].powi(2)).max(epsilon);
         let r_distance = r_squared.sqrt();
 
-        // F = G * m1 * m2 / r^2
-        let force_magnitude = params.g_constant * current.mass * other.mass / r_squared;
-        let force_direction = [r_vec[0] / r_distance, r_vec[1] / r_distance];
+        // a = G * m_other / r^2
+        let acceleration_magnitude = strength / r_squared;
+        let direction = [r_vec[0] / r_distance, r_vec[1] / r_distance];
 
-        force[0] += force_magnitude * force_direction[0];
-        force[1] += force_magnitude * force_direction[1];
+        acceleration[0] += acceleration_magnitude * direction[0];
+        acceleration[1] += acceleration_magnitude * direction[1];
     }
 
-    // Berechne Beschleunigung: a = F / m
-    let acceleration = [force[0] / current.mass, force[1] / current.mass];
+    if let Some(field) = external_field {
+        let external = field.acceleration(current.position);
+        acceleration[0] += external[0];
+        acceleration[1] += external[1];
+    }
+    if let Some(corrections) = post_newtonian {
+        acceleration[0] += corrections[index][0];
+        acceleration[1] += corrections[index][1];
+    }
 
-    // Update Geschwindigkeit: v = v + a * dt
-    let new_velocity = [
-        current.velocity[0] + acceleration[0] * params.dt,
-        current.velocity[1] + acceleration[1] * params.dt,
-    ];
+    // Update Geschwindigkeit: v = v + a * dt, im rotierenden System mit Coriolis- und Zentrifugalterm
+    let new_velocity = rotating_frame::kick(current.velocity, acceleration, current.position, params);
 
     // Update Position: x = x + v * dt
-    let new_position = [
+    let mut new_position = [
         current.position[0] + new_velocity[0] * params.dt,
         current.position[1] + new_velocity[1] * params.dt,
     ];
+    if is_periodic {
+        new_position = new_position.map(|x| periodic::wrap(x, params.box_size));
+    }
 
-    Body::new(new_position, new_velocity, current.mass)
+    Body { position: new_position, velocity: new_velocity, ..*current }
 }
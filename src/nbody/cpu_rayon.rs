@@ -1,8 +1,13 @@
 use crate::nbody::simulation_state::SimulationState;
 use crate::nbody::simulation_trait::Simulation;
 use crate::nbody::cpu_core;
+use crate::nbody::post_newtonian;
 use rayon::prelude::*;
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics::Trajectory;
+use crate::nbody::species::SpeciesTable;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use std::sync::Arc;
 
 pub struct CpuMultiThreaded {
     state: SimulationState,
@@ -15,16 +20,46 @@ impl CpuMultiThreaded {
         }
     }
 
+    pub fn set_external_field(&mut self, field: Option<Arc<dyn ExternalField>>) {
+        self.state.set_external_field(field);
+    }
+
+    pub fn set_trajectory(&mut self, index: usize, trajectory: Trajectory) {
+        self.state.set_trajectory(index, trajectory);
+    }
+
+    pub fn set_species_table(&mut self, table: Option<SpeciesTable>) {
+        self.state.set_species_table(table);
+    }
+
+    pub fn time(&self) -> f32 {
+        self.state.time()
+    }
+
     #[inline]
     fn step_once(&mut self) {
         let n = self.state.len();
         let bodies_ref = self.state.bodies();
+        let massive = self.state.massive();
+        let external_field = self.state.external_field();
+        let species = self.state.species();
         let params = self.state.params;
 
+        let post_newtonian = post_newtonian::is_enabled(&params).then(|| {
+            let fields: Vec<_> = (0..n)
+                .into_par_iter()
+                .map(|i| post_newtonian::newtonian_field(i, bodies_ref, massive, &params))
+                .collect();
+            (0..n)
+                .into_par_iter()
+                .map(|i| post_newtonian::correction(i, bodies_ref, massive, &params, &fields))
+                .collect::<Vec<_>>()
+        });
+
         let new_bodies: Vec<Body> = (0..n)
             .into_par_iter()
             .map(|i| {
-                cpu_core::compute_body_update(i, bodies_ref, &params)
+                cpu_core::compute_body_update(i, bodies_ref, massive, &params, external_field, post_newtonian.as_deref(), species)
             })
             .collect();
 
@@ -43,6 +78,10 @@ impl Simulation for CpuMultiThreaded {
         self.state.set_bodies(bodies);
     }
 
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        self.state.retain_bodies(kept);
+    }
+
     fn get_bodies(&self) -> Vec<Body> {
         self.state.get_bodies()
     }
@@ -52,6 +91,6 @@ impl Simulation for CpuMultiThreaded {
     }
 
     fn set_params(&mut self, simulation_params: SimulationParams) {
-        self.state.params = simulation_params;
+        self.state.set_params(simulation_params);
     }
 }
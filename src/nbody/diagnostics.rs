@@ -0,0 +1,76 @@
+//! Conserved quantities of a body set, accumulated in f64.
+//!
+//! The potential uses the same softening as the kernels: `r²` is floored at `epsilon`, so the
+//! energy of close pairs matches the forces the integrator actually applies.
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+
+pub fn kinetic_energy(bodies: &[Body]) -> f64 {
+    bodies
+        .iter()
+        .map(|b| {
+            let [vx, vy] = b.velocity.map(|v| v as f64);
+            0.5 * b.mass as f64 * (vx * vx + vy * vy)
+        })
+        .sum()
+}
+
+/// Pairwise potential energy, O(N²).
+pub fn potential_energy(bodies: &[Body], params: &SimulationParams) -> f64 {
+    let epsilon = params.epsilon as f64;
+    let mut potential = 0.0;
+    for (i, a) in bodies.iter().enumerate() {
+        for b in &bodies[i + 1..] {
+            let dx = (b.position[0] - a.position[0]) as f64;
+            let dy = (b.position[1] - a.position[1]) as f64;
+            let r = (dx * dx + dy * dy).max(epsilon).sqrt();
+            potential -= a.mass as f64 * b.mass as f64 / r;
+        }
+    }
+    params.g_constant as f64 * potential
+}
+
+pub fn total_energy(bodies: &[Body], params: &SimulationParams) -> f64 {
+    kinetic_energy(bodies) + potential_energy(bodies, params)
+}
+
+pub fn total_mass(bodies: &[Body]) -> f64 {
+    bodies.iter().map(|b| b.mass as f64).sum()
+}
+
+/// Centre-of-mass position and velocity.
+pub fn centre_of_mass(bodies: &[Body]) -> ([f64; 2], [f64; 2]) {
+    let total = total_mass(bodies);
+    if total == 0.0 {
+        return ([0.0; 2], [0.0; 2]);
+    }
+    let mut position = [0.0; 2];
+    let mut velocity = [0.0; 2];
+    for b in bodies {
+        let w = b.mass as f64 / total;
+        for k in 0..2 {
+            position[k] += w * b.position[k] as f64;
+            velocity[k] += w * b.velocity[k] as f64;
+        }
+    }
+    (position, velocity)
+}
+
+pub fn linear_momentum(bodies: &[Body]) -> [f64; 2] {
+    bodies.iter().fold([0.0; 2], |p, b| {
+        let m = b.mass as f64;
+        [p[0] + m * b.velocity[0] as f64, p[1] + m * b.velocity[1] as f64]
+    })
+}
+
+/// z component of the angular momentum about the origin.
+pub fn angular_momentum(bodies: &[Body]) -> f64 {
+    bodies
+        .iter()
+        .map(|b| b.mass as f64 * (b.position[0] as f64 * b.velocity[1] as f64 - b.position[1] as f64 * b.velocity[0] as f64))
+        .sum()
+}
+
+/// `Q = T / |W|`; 0.5 is virial equilibrium, 0 a cold start.
+pub fn virial_ratio(bodies: &[Body], params: &SimulationParams) -> f64 {
+    kinetic_energy(bodies) / potential_energy(bodies, params).abs()
+}
@@ -0,0 +1,265 @@
+//! Detection of bodies that left the system, around any `Simulation`.
+//!
+//! The energy test only sees what the monitor is told: the pair potential with the wrapped
+//! backend's `epsilon` and `g_constant`, the nearest image in a periodic box, plus the
+//! external field and species table handed to `external_field` and `species_table`. Ewald
+//! boxes have no such potential, so there only the radius test is available.
+use crate::nbody::diagnostics;
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics::Motion;
+use crate::nbody::periodic;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::simulation_trait::Simulation;
+use crate::nbody::species::SpeciesTable;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// What happens to a body once it counts as escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapePolicy {
+    /// Keep it in the simulation and record the escape once.
+    Log,
+    /// Drop it from the simulation.
+    Remove,
+    /// Drop it from the simulation and keep it in `escapers()`.
+    Collect,
+}
+
+/// A body that escaped; `id` is its index in the bodies the monitor started with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Escaper {
+    pub id: usize,
+    pub step: u64,
+    pub time: f32,
+    pub body: Body,
+}
+
+/// Wraps any `Simulation` and checks for escapers every `every` steps.
+///
+/// A body escapes when it is farther than `radius` from the centre of mass, or, with
+/// `unbound`, when its energy relative to the rest of the system is positive: the kinetic
+/// energy in the centre-of-mass frame plus the (softened) potential of all other bodies.
+/// Fixed and kinematic bodies are never flagged. Removing bodies renumbers the wrapped
+/// simulation through `retain_bodies`, which carries trajectories along; `ids()` maps its
+/// indices back to the original ones.
+pub struct EscapeMonitor<S: Simulation> {
+    inner: S,
+    every: usize,
+    policy: EscapePolicy,
+    radius: Option<f32>,
+    unbound: bool,
+    external_field: Option<Arc<dyn ExternalField>>,
+    species: Option<SpeciesTable>,
+    ids: Vec<usize>,
+    logged: HashSet<usize>,
+    escapers: Vec<Escaper>,
+    removed: usize,
+    steps_taken: u64,
+    time: f32,
+}
+
+impl<S: Simulation> EscapeMonitor<S> {
+    /// Flags unbound bodies by default, except in an Ewald box.
+    pub fn new(inner: S, every: usize, policy: EscapePolicy) -> Self {
+        assert!(every > 0, "check interval must be at least one step");
+        let ids = (0..inner.get_bodies().len()).collect();
+        let unbound = !periodic::uses_ewald(inner.get_params());
+        Self {
+            inner,
+            every,
+            policy,
+            radius: None,
+            unbound,
+            external_field: None,
+            species: None,
+            ids,
+            logged: HashSet::new(),
+            escapers: Vec::new(),
+            removed: 0,
+            steps_taken: 0,
+            time: 0.0,
+        }
+    }
+
+    /// Also flag bodies beyond `radius` from the centre of mass.
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    /// Whether positive-energy bodies count as escaped.
+    pub fn unbound(mut self, unbound: bool) -> Self {
+        self.unbound = unbound;
+        self
+    }
+
+    /// The wrapped backend's external field, whose potential the energy test adds.
+    pub fn external_field(mut self, field: Arc<dyn ExternalField>) -> Self {
+        assert!(field.potential([0.0, 0.0]).is_some(), "External field has no potential for the energy test");
+        self.external_field = Some(field);
+        self
+    }
+
+    /// The wrapped backend's species table, whose couplings and softening the energy test uses.
+    pub fn species_table(mut self, table: SpeciesTable) -> Self {
+        self.species = Some(table);
+        self
+    }
+
+    /// Escapes in the order they were found; empty under `EscapePolicy::Remove`.
+    pub fn escapers(&self) -> &[Escaper] {
+        &self.escapers
+    }
+
+    /// Number of bodies taken out of the simulation so far.
+    #[inline]
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Original index of each body in the wrapped simulation.
+    #[inline]
+    pub fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    #[inline]
+    pub fn steps_taken(&self) -> u64 {
+        self.steps_taken
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Indices of the bodies that currently count as escaped.
+    pub fn find_escapers(&self, bodies: &[Body]) -> Vec<usize> {
+        let params = self.inner.get_params();
+        assert!(
+            !(self.unbound && periodic::uses_ewald(params)),
+            "The energy test has no Ewald potential, use unbound(false)"
+        );
+        let (centre, drift) = diagnostics::centre_of_mass(bodies);
+        (0..bodies.len())
+            .filter(|&i| bodies[i].motion() == Motion::Free)
+            .filter(|&i| {
+                let body = &bodies[i];
+                let dx = body.position[0] as f64 - centre[0];
+                let dy = body.position[1] as f64 - centre[1];
+                let beyond = self.radius.is_some_and(|r| dx.hypot(dy) > r as f64);
+                beyond || (self.unbound && self.specific_energy(i, bodies, drift, params) > 0.0)
+            })
+            .collect()
+    }
+
+    /// Checks for escapers right away and applies the policy.
+    pub fn check(&mut self) {
+        let bodies = self.inner.get_bodies();
+        let escaped = self.find_escapers(&bodies);
+        if escaped.is_empty() {
+            return;
+        }
+
+        let (step, time) = (self.steps_taken, self.time);
+        let record = |i: usize| Escaper { id: self.ids[i], step, time, body: bodies[i] };
+        match self.policy {
+            EscapePolicy::Log => {
+                let new: Vec<Escaper> =
+                    escaped.into_iter().filter(|&i| !self.logged.contains(&self.ids[i])).map(record).collect();
+                self.logged.extend(new.iter().map(|e| e.id));
+                self.escapers.extend(new);
+            }
+            EscapePolicy::Remove | EscapePolicy::Collect => {
+                if self.policy == EscapePolicy::Collect {
+                    let new: Vec<Escaper> = escaped.iter().map(|&i| record(i)).collect();
+                    self.escapers.extend(new);
+                }
+                let escaped: HashSet<usize> = escaped.into_iter().collect();
+                self.removed += escaped.len();
+                let kept: Vec<usize> = (0..bodies.len()).filter(|i| !escaped.contains(i)).collect();
+                self.ids = kept.iter().map(|&i| self.ids[i]).collect();
+                self.inner.retain_bodies(&kept);
+            }
+        }
+    }
+
+    /// Energy per unit mass of body `index` in the centre-of-mass frame of `bodies`.
+    fn specific_energy(&self, index: usize, bodies: &[Body], drift: [f64; 2], params: &SimulationParams) -> f64 {
+        let body = &bodies[index];
+        let vx = body.velocity[0] as f64 - drift[0];
+        let vy = body.velocity[1] as f64 - drift[1];
+        let mut potential = self
+            .external_field
+            .as_ref()
+            .and_then(|field| field.potential(body.position))
+            .unwrap_or(0.0) as f64;
+        for (j, other) in bodies.iter().enumerate() {
+            if j == index || other.mass == 0.0 {
+                continue;
+            }
+            let (coupling, epsilon) = self.species.map_or((1.0, params.epsilon), |t| t.pair(body, other));
+            let mut d = [other.position[0] - body.position[0], other.position[1] - body.position[1]];
+            if periodic::is_periodic(params) {
+                d = d.map(|x| periodic::minimum_image(x, params.box_size));
+            }
+            let [dx, dy] = d.map(|x| x as f64);
+            let r = (dx * dx + dy * dy).max(epsilon as f64).sqrt();
+            potential -= (coupling * params.g_constant) as f64 * other.mass as f64 / r;
+        }
+        0.5 * (vx * vx + vy * vy) + potential
+    }
+}
+
+impl<S: Simulation> Simulation for EscapeMonitor<S> {
+    fn step(&mut self, steps: usize) {
+        let mut remaining = steps;
+        while remaining > 0 {
+            let until_check = self.every - (self.steps_taken % self.every as u64) as usize;
+            let chunk = until_check.min(remaining);
+
+            self.inner.step(chunk);
+            self.steps_taken += chunk as u64;
+            self.time += chunk as f32 * self.inner.get_params().dt;
+            remaining -= chunk;
+
+            if self.steps_taken.is_multiple_of(self.every as u64) {
+                self.check();
+            }
+        }
+    }
+
+    fn get_bodies(&self) -> Vec<Body> {
+        self.inner.get_bodies()
+    }
+
+    /// Starts a new numbering: the given bodies get ids `0..len`.
+    fn set_bodies(&mut self, bodies: Vec<Body>) {
+        self.ids = (0..bodies.len()).collect();
+        self.logged.clear();
+        self.inner.set_bodies(bodies);
+    }
+
+    /// Keeps the original ids of the bodies that stay.
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        self.ids = kept.iter().map(|&i| self.ids[i]).collect();
+        self.inner.retain_bodies(kept);
+    }
+
+    fn get_params(&self) -> &SimulationParams {
+        self.inner.get_params()
+    }
+
+    fn set_params(&mut self, simulation_params: SimulationParams) {
+        self.inner.set_params(simulation_params);
+    }
+}
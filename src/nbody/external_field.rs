@@ -0,0 +1,199 @@
+//! Fixed background potentials added to the self-gravity of every body.
+//!
+//! Fields are evaluated in the simulation plane (z = 0) and return an acceleration, so they
+//! carry their own `G`: strengths are given as `GM` or `v0²`. The analytic fields can also
+//! describe themselves as `FieldTerm`s for the WGSL kernel, which takes up to
+//! `MAX_GPU_FIELD_TERMS` of them; closures only run on the CPU.
+use crate::nbody::shader_types::nbody::{ExternalFields, FieldTerm};
+use std::sync::Arc;
+
+pub const MAX_GPU_FIELD_TERMS: usize = 4;
+
+const KIND_SPHERICAL: u32 = 1;
+const KIND_LOGARITHMIC: u32 = 2;
+
+pub trait ExternalField: Send + Sync {
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2];
+
+    /// Potential at `position`, `None` if the field doesn't know it.
+    fn potential(&self, _position: [f32; 2]) -> Option<f32> {
+        None
+    }
+
+    /// The field as terms the GPU kernel can evaluate, `None` if it has no analytic form.
+    fn gpu_terms(&self) -> Option<Vec<FieldTerm>> {
+        None
+    }
+}
+
+/// `-GM d / (|d|² + s²)^(3/2)`, shared by the point mass and the in-plane Miyamoto–Nagai disk.
+#[inline]
+fn spherical_acceleration(gm: f32, scale_squared: f32, centre: [f32; 2], position: [f32; 2]) -> [f32; 2] {
+    let d = [position[0] - centre[0], position[1] - centre[1]];
+    let r_squared = d[0] * d[0] + d[1] * d[1] + scale_squared;
+    let factor = -gm / (r_squared * r_squared.sqrt());
+    [factor * d[0], factor * d[1]]
+}
+
+/// `-GM / sqrt(|d|² + s²)`, the potential of `spherical_acceleration`.
+#[inline]
+fn spherical_potential(gm: f32, scale_squared: f32, centre: [f32; 2], position: [f32; 2]) -> f32 {
+    let d = [position[0] - centre[0], position[1] - centre[1]];
+    -gm / (d[0] * d[0] + d[1] * d[1] + scale_squared).sqrt()
+}
+
+/// Point mass `GM` at `centre` with Plummer softening length `softening`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointMassField {
+    pub gm: f32,
+    pub softening: f32,
+    pub centre: [f32; 2],
+}
+
+impl PointMassField {
+    pub fn new(gm: f32, softening: f32) -> Self {
+        Self { gm, softening, centre: [0.0, 0.0] }
+    }
+}
+
+impl ExternalField for PointMassField {
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2] {
+        spherical_acceleration(self.gm, self.softening * self.softening, self.centre, position)
+    }
+
+    fn potential(&self, position: [f32; 2]) -> Option<f32> {
+        Some(spherical_potential(self.gm, self.softening * self.softening, self.centre, position))
+    }
+
+    fn gpu_terms(&self) -> Option<Vec<FieldTerm>> {
+        let scale = self.softening * self.softening;
+        Some(vec![FieldTerm::new(KIND_SPHERICAL, self.gm, scale, 0.0, self.centre)])
+    }
+}
+
+/// `Φ = ½ v0² ln(R_c² + x² + y²/q²)`: flat rotation curve `v0` outside the core radius `R_c`,
+/// flattened along y by `q`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogarithmicField {
+    pub v0: f32,
+    pub core_radius: f32,
+    pub flattening: f32,
+    pub centre: [f32; 2],
+}
+
+impl LogarithmicField {
+    pub fn new(v0: f32, core_radius: f32, flattening: f32) -> Self {
+        Self { v0, core_radius, flattening, centre: [0.0, 0.0] }
+    }
+}
+
+impl ExternalField for LogarithmicField {
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2] {
+        let d = [position[0] - self.centre[0], position[1] - self.centre[1]];
+        let inverse_q2 = 1.0 / (self.flattening * self.flattening);
+        let m_squared = self.core_radius * self.core_radius + d[0] * d[0] + d[1] * d[1] * inverse_q2;
+        let factor = -self.v0 * self.v0 / m_squared;
+        [factor * d[0], factor * d[1] * inverse_q2]
+    }
+
+    fn potential(&self, position: [f32; 2]) -> Option<f32> {
+        let d = [position[0] - self.centre[0], position[1] - self.centre[1]];
+        let m_squared = self.core_radius * self.core_radius + d[0] * d[0] + d[1] * d[1] / (self.flattening * self.flattening);
+        Some(0.5 * self.v0 * self.v0 * m_squared.ln())
+    }
+
+    fn gpu_terms(&self) -> Option<Vec<FieldTerm>> {
+        Some(vec![FieldTerm::new(
+            KIND_LOGARITHMIC,
+            self.v0 * self.v0,
+            self.core_radius * self.core_radius,
+            1.0 / (self.flattening * self.flattening),
+            self.centre,
+        )])
+    }
+}
+
+/// Miyamoto & Nagai (1975) disk of mass `GM`, scale length `a` and scale height `b`, seen
+/// face-on: in its midplane `Φ = -GM / sqrt(R² + (a + b)²)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MiyamotoNagaiField {
+    pub gm: f32,
+    pub a: f32,
+    pub b: f32,
+    pub centre: [f32; 2],
+}
+
+impl MiyamotoNagaiField {
+    pub fn new(gm: f32, a: f32, b: f32) -> Self {
+        Self { gm, a, b, centre: [0.0, 0.0] }
+    }
+}
+
+impl ExternalField for MiyamotoNagaiField {
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2] {
+        spherical_acceleration(self.gm, (self.a + self.b).powi(2), self.centre, position)
+    }
+
+    fn potential(&self, position: [f32; 2]) -> Option<f32> {
+        Some(spherical_potential(self.gm, (self.a + self.b).powi(2), self.centre, position))
+    }
+
+    fn gpu_terms(&self) -> Option<Vec<FieldTerm>> {
+        Some(vec![FieldTerm::new(KIND_SPHERICAL, self.gm, (self.a + self.b).powi(2), 0.0, self.centre)])
+    }
+}
+
+/// Any `Fn(position) -> acceleration`, CPU backends only.
+pub struct ClosureField<F>(pub F);
+
+impl<F> ExternalField for ClosureField<F>
+where
+    F: Fn([f32; 2]) -> [f32; 2] + Send + Sync,
+{
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2] {
+        (self.0)(position)
+    }
+}
+
+/// Sum of several fields, e.g. bulge, disk and halo of a galaxy.
+#[derive(Clone, Default)]
+pub struct CompositeField(pub Vec<Arc<dyn ExternalField>>);
+
+impl ExternalField for CompositeField {
+    fn acceleration(&self, position: [f32; 2]) -> [f32; 2] {
+        self.0.iter().fold([0.0, 0.0], |sum, field| {
+            let a = field.acceleration(position);
+            [sum[0] + a[0], sum[1] + a[1]]
+        })
+    }
+
+    fn potential(&self, position: [f32; 2]) -> Option<f32> {
+        self.0.iter().map(|field| field.potential(position)).sum()
+    }
+
+    fn gpu_terms(&self) -> Option<Vec<FieldTerm>> {
+        let mut terms = Vec::new();
+        for field in &self.0 {
+            terms.extend(field.gpu_terms()?);
+        }
+        Some(terms)
+    }
+}
+
+/// Packs a field into the GPU uniform; `None` clears it.
+pub(crate) fn gpu_uniform(field: Option<&dyn ExternalField>) -> ExternalFields {
+    let mut terms = [FieldTerm::new(0, 0.0, 0.0, 0.0, [0.0, 0.0]); MAX_GPU_FIELD_TERMS];
+    let Some(field) = field else {
+        return ExternalFields::new(0, terms);
+    };
+
+    let field_terms = field.gpu_terms().expect("External field has no analytic form for the GPU");
+    assert!(
+        field_terms.len() <= MAX_GPU_FIELD_TERMS,
+        "The GPU kernel takes at most {} field terms, got {}",
+        MAX_GPU_FIELD_TERMS,
+        field_terms.len()
+    );
+    terms[..field_terms.len()].copy_from_slice(&field_terms);
+    ExternalFields::new(field_terms.len() as u32, terms)
+}
@@ -1,11 +1,18 @@
 /// GPU N-Body Simulation mit WGPU - Double-Buffering wie CPU-Version
+use crate::nbody::external_field::{self, ExternalField};
+use crate::nbody::kinematics::Trajectory;
+use crate::nbody::post_newtonian;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
-use crate::nbody::simulation_state::SimulationState;
+use crate::nbody::simulation_state::{massive_indices, SimulationState};
+use crate::nbody::species::{self, SpeciesTable};
 use crate::nbody::simulation_trait::Simulation;
 use wgpu::util::DeviceExt;
 use wgpu::wgt::PollType;
+use std::sync::Arc;
 
 pub struct GpuSimulator {
+    /// Params, settings, masses and flags. Positions and velocities are only current as of
+    /// the last `set_bodies`: `step` leaves them on the GPU, so read them with `get_bodies()`.
     state: SimulationState,
     device: wgpu::Device,
     queue: wgpu::Queue,
@@ -15,11 +22,34 @@ pub struct GpuSimulator {
     bodies_buffer_b: wgpu::Buffer,
     params_buffer: wgpu::Buffer,
     n_bodies_buffer: wgpu::Buffer,
+    external_fields_buffer: wgpu::Buffer,
+    massive_buffer: wgpu::Buffer,
+    n_massive_buffer: wgpu::Buffer,
+    species_buffer: wgpu::Buffer,
     current_buffer_is_a: bool,
 }
 
+/// Massive body indices as the shader reads them; storage buffers may not be empty.
+fn massive_contents(bodies: &[Body]) -> (Vec<u32>, u32) {
+    let mut indices: Vec<u32> = massive_indices(bodies).into_iter().map(|i| i as u32).collect();
+    let count = indices.len() as u32;
+    indices.resize(bodies.len().max(1), 0);
+    (indices, count)
+}
+
+/// Storage buffer of `size` bytes, for the ones `set_bodies` replaces when the bodies outgrow them.
+fn storage_buffer(device: &wgpu::Device, label: &str, size: u64, usage: wgpu::BufferUsages) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | usage,
+        mapped_at_creation: false,
+    })
+}
+
 impl GpuSimulator {
     pub async fn new(bodies: Vec<Body>, params: SimulationParams) -> Self {
+        assert!(!post_newtonian::is_enabled(&params), "1PN corrections are not available on the GPU");
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -65,6 +95,31 @@ impl GpuSimulator {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let external_fields_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("External Fields Buffer"),
+            contents: bytemuck::bytes_of(&external_field::gpu_uniform(None)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let (massive, n_massive) = massive_contents(&bodies);
+        let massive_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Massive Indices Buffer"),
+            contents: bytemuck::cast_slice(&massive),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let n_massive_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("N Massive Buffer"),
+            contents: bytemuck::bytes_of(&n_massive),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let species_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Species Buffer"),
+            contents: bytemuck::bytes_of(&species::gpu_uniform(None)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
         println!("GPU Setup: {} bodies, buffer size A: {} bytes, buffer size B: {} bytes",
                  bodies.len(),
                  bodies.len() * std::mem::size_of::<Body>(),
@@ -139,6 +194,46 @@ impl GpuSimulator {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 7,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -167,10 +262,48 @@ impl GpuSimulator {
             bodies_buffer_b,
             params_buffer,
             n_bodies_buffer,
+            external_fields_buffer,
+            massive_buffer,
+            n_massive_buffer,
+            species_buffer,
             current_buffer_is_a: true,
         }
     }
 
+    /// Only analytic fields (`ExternalField::gpu_terms`) run on the GPU; panics otherwise.
+    pub fn set_external_field(&mut self, field: Option<Arc<dyn ExternalField>>) {
+        let uniform = external_field::gpu_uniform(field.as_deref());
+        self.queue.write_buffer(&self.external_fields_buffer, 0, bytemuck::bytes_of(&uniform));
+        self.state.set_external_field(field);
+    }
+
+    pub fn set_species_table(&mut self, table: Option<SpeciesTable>) {
+        let uniform = species::gpu_uniform(table.as_ref());
+        self.queue.write_buffer(&self.species_buffer, 0, bytemuck::bytes_of(&uniform));
+        self.state.set_species_table(table);
+    }
+
+    /// Trajectories are evaluated on the CPU, so steps are submitted one at a time while any
+    /// body has one. `set_bodies` drops them like `SimulationState::set_bodies`.
+    pub fn set_trajectory(&mut self, index: usize, trajectory: Trajectory) {
+        self.state.set_trajectory(index, trajectory);
+        self.write_trajectory_bodies();
+    }
+
+    pub fn time(&self) -> f32 {
+        self.state.time()
+    }
+
+    /// Takes only mass and flags from the stale `state`, which the kernel never changes.
+    fn write_trajectory_bodies(&self) {
+        for (index, trajectory) in self.state.trajectories() {
+            let (position, velocity) = trajectory(self.state.time());
+            let body = Body { position, velocity, ..self.state.bodies()[*index] };
+            let offset = (*index * std::mem::size_of::<Body>()) as u64;
+            self.queue.write_buffer(self.get_active_buffer(), offset, bytemuck::bytes_of(&body));
+        }
+    }
+
     #[inline]
     fn get_active_buffer(&self) -> &wgpu::Buffer {
         if self.current_buffer_is_a {
@@ -190,11 +323,11 @@ impl GpuSimulator {
     }
 }
 
-impl Simulation for GpuSimulator {
-    fn step(&mut self, steps: usize) {
+impl GpuSimulator {
+    fn dispatch(&mut self, steps: usize) {
         let n = self.state.len() as u32;
         let workgroup_size = 256;
-        let num_workgroups = (n + workgroup_size - 1) / workgroup_size;
+        let num_workgroups = n.div_ceil(workgroup_size);
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("N-Body Compute Encoder"),
@@ -224,6 +357,22 @@ impl Simulation for GpuSimulator {
                         binding: 3,
                         resource: self.n_bodies_buffer.as_entire_binding(),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: self.external_fields_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: self.massive_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 6,
+                        resource: self.n_massive_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 7,
+                        resource: self.species_buffer.as_entire_binding(),
+                    },
                 ],
             });
 
@@ -248,8 +397,22 @@ impl Simulation for GpuSimulator {
             submission_index: Some(submission_index),
             timeout: None,
         }).expect("Failed to poll device");
+    }
+}
 
-        self.state.update_bodies(self.get_bodies());
+impl Simulation for GpuSimulator {
+    fn step(&mut self, steps: usize) {
+        let dt = self.state.get_params().dt;
+        if self.state.trajectories().is_empty() {
+            self.dispatch(steps);
+            self.state.set_time(self.state.time() + steps as f32 * dt);
+            return;
+        }
+        for _ in 0..steps {
+            self.dispatch(1);
+            self.state.set_time(self.state.time() + dt);
+            self.write_trajectory_bodies();
+        }
     }
 
     fn get_bodies(&self) -> Vec<Body> {
@@ -295,14 +458,38 @@ impl Simulation for GpuSimulator {
         bodies
     }
 
+    /// Grows the body and massive index buffers when `bodies` no longer fit; the bind group
+    /// is created per dispatch and picks the new buffers up.
     fn set_bodies(&mut self, bodies: Vec<Body>) {
         self.state.set_bodies(bodies.clone());
 
+        let bodies_size = std::mem::size_of_val(bodies.as_slice()) as u64;
+        if bodies_size > self.bodies_buffer_a.size() {
+            let usage = wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC;
+            self.bodies_buffer_a = storage_buffer(&self.device, "Bodies Buffer A", bodies_size, usage);
+            self.bodies_buffer_b = storage_buffer(&self.device, "Bodies Buffer B", bodies_size, usage);
+        }
+        let (massive, n_massive) = massive_contents(&bodies);
+        let massive_size = std::mem::size_of_val(massive.as_slice()) as u64;
+        if massive_size > self.massive_buffer.size() {
+            self.massive_buffer =
+                storage_buffer(&self.device, "Massive Indices Buffer", massive_size, wgpu::BufferUsages::COPY_DST);
+        }
+
         let target_buffer = self.get_active_buffer();
         self.queue.write_buffer(target_buffer, 0, bytemuck::cast_slice(&bodies));
 
         let n_bodies = bodies.len() as u32;
         self.queue.write_buffer(&self.n_bodies_buffer, 0, bytemuck::bytes_of(&n_bodies));
+
+        self.queue.write_buffer(&self.massive_buffer, 0, bytemuck::cast_slice(&massive));
+        self.queue.write_buffer(&self.n_massive_buffer, 0, bytemuck::bytes_of(&n_massive));
+    }
+
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        let bodies = self.get_bodies();
+        self.state.remap_trajectories(kept);
+        self.set_bodies(kept.iter().map(|&i| bodies[i]).collect());
     }
 
     fn get_params(&self) -> &SimulationParams {
@@ -310,7 +497,8 @@ impl Simulation for GpuSimulator {
     }
 
     fn set_params(&mut self, simulation_params: SimulationParams) {
-        self.state.params = simulation_params;
+        assert!(!post_newtonian::is_enabled(&simulation_params), "1PN corrections are not available on the GPU");
+        self.state.set_params(simulation_params);
 
         self.queue.write_buffer(
             &self.params_buffer,
@@ -0,0 +1,111 @@
+//! Two body sets on a parabolic or hyperbolic encounter orbit.
+//!
+//! Each set is moved into its own centre-of-mass frame, tilted by its inclination about the
+//! line from the orbit's focus to pericentre (the x axis) and projected back onto the plane,
+//! then placed on the incoming branch of the two-body conic through the chosen pericentre.
+//! An inclination of 0 is a prograde encounter, π a retrograde one.
+use crate::nbody::initial_conditions::{lift, project, recentre, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+
+/// Builds an encounter of two galaxies; `build` returns the first set's bodies first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollisionBuilder {
+    pericentre: f64,
+    eccentricity: f64,
+    separation: f64,
+    inclinations: [f64; 2],
+    g_constant: f64,
+}
+
+impl CollisionBuilder {
+    /// Parabolic, prograde encounter starting 10 pericentre distances apart, G = 1.
+    pub fn new(pericentre: f64) -> Self {
+        assert!(pericentre > 0.0, "pericentre distance must be positive");
+        Self {
+            pericentre,
+            eccentricity: 1.0,
+            separation: 10.0 * pericentre,
+            inclinations: [0.0, 0.0],
+            g_constant: 1.0,
+        }
+    }
+
+    /// 1 for a parabolic orbit, above 1 for a hyperbolic one.
+    pub fn eccentricity(mut self, eccentricity: f64) -> Self {
+        assert!(eccentricity >= 1.0, "only unbound (e >= 1) encounters are supported");
+        self.eccentricity = eccentricity;
+        self
+    }
+
+    /// Initial distance between the two centres of mass, at least the pericentre distance.
+    pub fn separation(mut self, separation: f64) -> Self {
+        assert!(separation >= self.pericentre, "initial separation is inside pericentre");
+        self.separation = separation;
+        self
+    }
+
+    /// Disk inclinations of the two sets against the orbital plane, in radians.
+    pub fn inclinations(mut self, first: f64, second: f64) -> Self {
+        self.inclinations = [first, second];
+        self
+    }
+
+    /// Must match the `g_constant` the bodies are simulated with.
+    pub fn g_constant(mut self, g_constant: f64) -> Self {
+        self.g_constant = g_constant;
+        self
+    }
+
+    pub fn build(&self, first: &[Body], second: &[Body]) -> Vec<Body> {
+        let mut a = lift(first);
+        let mut b = lift(second);
+        let m1: f64 = a.iter().map(|p| p.2).sum();
+        let m2: f64 = b.iter().map(|p| p.2).sum();
+        let total = m1 + m2;
+        assert!(m1 > 0.0 && m2 > 0.0, "both body sets need positive total mass");
+
+        // Relative orbit of the second set around the first, pericentre on the +x axis
+        let e = self.eccentricity;
+        let semi_latus = self.pericentre * (1.0 + e);
+        let cos_nu = ((semi_latus / self.separation - 1.0) / e).clamp(-1.0, 1.0);
+        let nu = -cos_nu.acos();
+        let (sin_nu, cos_nu) = nu.sin_cos();
+        let speed_unit = (self.g_constant * total / semi_latus).sqrt();
+        let v_radial = speed_unit * e * sin_nu;
+        let v_tangential = speed_unit * (1.0 + e * cos_nu);
+        let offset = [self.separation * cos_nu, self.separation * sin_nu, 0.0];
+        let velocity = [
+            v_radial * cos_nu - v_tangential * sin_nu,
+            v_radial * sin_nu + v_tangential * cos_nu,
+            0.0,
+        ];
+
+        for (particles, inclination, weight) in [
+            (&mut a, self.inclinations[0], -m2 / total),
+            (&mut b, self.inclinations[1], m1 / total),
+        ] {
+            recentre(particles);
+            let (sin_i, cos_i) = inclination.sin_cos();
+            for (x, v, _) in particles.iter_mut() {
+                tilt(x, sin_i, cos_i);
+                tilt(v, sin_i, cos_i);
+                for k in 0..3 {
+                    x[k] += weight * offset[k];
+                    v[k] += weight * velocity[k];
+                }
+            }
+        }
+
+        let mut particles: Vec<Particle3> = a;
+        particles.extend(b);
+        recentre(&mut particles);
+        project(&particles)
+    }
+}
+
+/// Rotates about the x axis.
+fn tilt(vector: &mut [f64; 3], sin_i: f64, cos_i: f64) {
+    let [_, y, z] = *vector;
+    vector[1] = y * cos_i - z * sin_i;
+    vector[2] = y * sin_i + z * cos_i;
+}
@@ -0,0 +1,74 @@
+//! Planar disks: a uniform cold disk for collapse tests and a rotating Kuzmin disk.
+//!
+//! Both live entirely in the simulation plane. `velocity_noise` adds an isotropic Gaussian
+//! with that dispersion per component; pass 0 for the noiseless models. G = 1.
+use crate::nbody::initial_conditions::{project, recentre, standard_normal, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+use std::f64::consts::TAU;
+
+/// Kuzmin disk radii beyond the circle holding this mass fraction are redrawn.
+const KUZMIN_MASS_CUTOFF: f64 = 0.99;
+
+fn noisy_velocity<R: Rng + ?Sized>(rng: &mut R, velocity: [f64; 2], noise: f64) -> [f64; 3] {
+    [
+        velocity[0] + noise * standard_normal(rng),
+        velocity[1] + noise * standard_normal(rng),
+        0.0,
+    ]
+}
+
+/// Uniform disk of `radius` at rest apart from the velocity noise. Without noise it collapses
+/// homologously onto the centre, which makes it a stress test for softening and time steps.
+pub fn generate_uniform_disk<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    radius: f64,
+    total_mass: f64,
+    velocity_noise: f64,
+) -> Vec<Body> {
+    let mut particles: Vec<Particle3> = (0..n)
+        .map(|_| {
+            let r = radius * rng.random_range(0.0f64..1.0).sqrt();
+            let (sin, cos) = rng.random_range(0.0..TAU).sin_cos();
+            ([r * cos, r * sin, 0.0], noisy_velocity(rng, [0.0, 0.0], velocity_noise), total_mass / n as f64)
+        })
+        .collect();
+    recentre(&mut particles);
+    project(&particles)
+}
+
+/// Kuzmin (1956) disk, `Σ(R) = M a / (2π (R² + a²)^(3/2))`, on circular orbits. Its potential in
+/// the plane is that of a point mass a distance `a` off the plane, so the rotation curve
+/// `v² = M R² / (R² + a²)^(3/2)` is exact for the in-plane force. Truncated at the radius
+/// holding 99% of the mass; the truncation is ignored for the velocities.
+pub fn generate_kuzmin_disk<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    total_mass: f64,
+    scale_length: f64,
+    velocity_noise: f64,
+) -> Vec<Body> {
+    let a = scale_length;
+    let mass = total_mass / n as f64;
+    let mut particles: Vec<Particle3> = Vec::with_capacity(n);
+    while particles.len() < n {
+        // Invert M(<R) = M (1 - a / sqrt(R² + a²))
+        let u: f64 = rng.random_range(0.0..KUZMIN_MASS_CUTOFF);
+        let r = a * ((1.0 - u).powi(-2) - 1.0).sqrt();
+        let v = (total_mass * r * r / (r * r + a * a).powf(1.5)).sqrt();
+        let (sin, cos) = rng.random_range(0.0..TAU).sin_cos();
+        let x = [r * cos, r * sin];
+        let velocity = [-v * sin, v * cos];
+
+        // Bodies come in mirrored pairs, otherwise the heavy tail drags the centre of mass
+        // well off the origin
+        particles.push(([x[0], x[1], 0.0], noisy_velocity(rng, velocity, velocity_noise), mass));
+        if particles.len() < n {
+            let mirrored = [-velocity[0], -velocity[1]];
+            particles.push(([-x[0], -x[1], 0.0], noisy_velocity(rng, mirrored, velocity_noise), mass));
+        }
+    }
+    recentre(&mut particles);
+    project(&particles)
+}
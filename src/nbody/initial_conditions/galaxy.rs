@@ -0,0 +1,191 @@
+//! Disk galaxy: exponential disk with an optional Hernquist bulge and NFW dark halo.
+//!
+//! Disk bodies rotate on the exact razor-thin exponential-disk rotation curve (Freeman 1970)
+//! plus the enclosed mass of the spherical components. Radial dispersion follows from a
+//! constant Toomre Q, `σ_R = Q 3.36 G Σ / κ`, the azimuthal one from the epicyclic ratio and the
+//! mean rotation is lowered by the asymmetric drift. Bulge and halo are sampled from their own
+//! distribution functions, i.e. in equilibrium in their own potential only. G = 1.
+use crate::nbody::initial_conditions::profiles::{hernquist_model, nfw_model, SphericalModel};
+use crate::nbody::initial_conditions::{project, recentre, standard_normal, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Disk bodies beyond this many scale lengths are redrawn.
+const DISK_CUTOFF: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BulgeSpec {
+    bodies: usize,
+    mass: f64,
+    scale_radius: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HaloSpec {
+    bodies: usize,
+    virial_mass: f64,
+    scale_radius: f64,
+    concentration: f64,
+}
+
+/// Builds a disk galaxy; `build` returns the disk bodies first, then bulge, then halo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GalaxyBuilder {
+    disk_bodies: usize,
+    disk_mass: f64,
+    scale_length: f64,
+    toomre_q: f64,
+    bulge: Option<BulgeSpec>,
+    halo: Option<HaloSpec>,
+}
+
+impl GalaxyBuilder {
+    /// Disk of mass 1 and scale length 1 with Q = 1.5, no bulge and no halo.
+    pub fn new(disk_bodies: usize) -> Self {
+        Self {
+            disk_bodies,
+            disk_mass: 1.0,
+            scale_length: 1.0,
+            toomre_q: 1.5,
+            bulge: None,
+            halo: None,
+        }
+    }
+
+    pub fn disk_mass(mut self, mass: f64) -> Self {
+        self.disk_mass = mass;
+        self
+    }
+
+    pub fn scale_length(mut self, scale_length: f64) -> Self {
+        self.scale_length = scale_length;
+        self
+    }
+
+    pub fn toomre_q(mut self, q: f64) -> Self {
+        self.toomre_q = q;
+        self
+    }
+
+    pub fn bulge(mut self, bodies: usize, mass: f64, scale_radius: f64) -> Self {
+        self.bulge = Some(BulgeSpec { bodies, mass, scale_radius });
+        self
+    }
+
+    pub fn halo(mut self, bodies: usize, virial_mass: f64, scale_radius: f64, concentration: f64) -> Self {
+        self.halo = Some(HaloSpec { bodies, virial_mass, scale_radius, concentration });
+        self
+    }
+
+    pub fn build<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<Body> {
+        let bulge_model = self.bulge.map(|b| hernquist_model(b.mass, b.scale_radius));
+        let halo_model = self.halo.map(|h| nfw_model(h.virial_mass, h.scale_radius, h.concentration));
+        let spheres: Vec<&SphericalModel> = bulge_model.iter().chain(halo_model.iter()).collect();
+
+        let r_d = self.scale_length;
+        let surface_density_0 = self.disk_mass / (2.0 * PI * r_d * r_d);
+        let circular_speed_squared = |r: f64| {
+            let y = r / (2.0 * r_d);
+            let disk = 2.0 * self.disk_mass / r_d * y * y * (bessel_i0(y) * bessel_k0(y) - bessel_i1(y) * bessel_k1(y));
+            let spherical: f64 = spheres.iter().map(|m| m.enclosed_mass_at(r) / r).sum();
+            disk + spherical
+        };
+
+        let disk_mass_per_body = self.disk_mass / self.disk_bodies.max(1) as f64;
+        let mut particles: Vec<Particle3> = Vec::with_capacity(self.disk_bodies);
+        for _ in 0..self.disk_bodies {
+            // Σ ∝ R e^(-R/R_d) in R is a gamma(2) distribution
+            let r = loop {
+                let u1: f64 = rng.random_range(f64::EPSILON..1.0);
+                let u2: f64 = rng.random_range(f64::EPSILON..1.0);
+                let r = -r_d * (u1 * u2).ln();
+                if r < DISK_CUTOFF * r_d {
+                    break r;
+                }
+            };
+
+            let v_c2 = circular_speed_squared(r);
+            let h = 1e-3 * r_d;
+            let dv2_dr = (circular_speed_squared(r + h) - circular_speed_squared((r - h).max(0.5 * r))) / (r + h - (r - h).max(0.5 * r));
+            let omega2 = v_c2 / (r * r);
+            let kappa2 = (dv2_dr / r + 2.0 * omega2).max(f64::MIN_POSITIVE);
+            let kappa = kappa2.sqrt();
+
+            let sigma_r = self.toomre_q * 3.36 * surface_density_0 * (-r / r_d).exp() / kappa;
+            let sigma_phi = sigma_r * kappa / (2.0 * omega2.sqrt());
+            let mean_v_phi2 = v_c2 + sigma_r * sigma_r * (1.0 - kappa2 / (4.0 * omega2) - 2.0 * r / r_d);
+            let mean_v_phi = mean_v_phi2.max(0.0).sqrt();
+
+            let v_r = sigma_r * standard_normal(rng);
+            let v_phi = mean_v_phi + sigma_phi * standard_normal(rng);
+            let phi: f64 = rng.random_range(0.0..std::f64::consts::TAU);
+            let (sin, cos) = phi.sin_cos();
+            particles.push((
+                [r * cos, r * sin, 0.0],
+                [v_r * cos - v_phi * sin, v_r * sin + v_phi * cos, 0.0],
+                disk_mass_per_body,
+            ));
+        }
+
+        if let (Some(spec), Some(model)) = (self.bulge, &bulge_model) {
+            particles.extend(model.sample(rng, spec.bodies, spec.mass, 1.0, 1.0));
+        }
+        if let (Some(spec), Some(model)) = (self.halo, &halo_model) {
+            particles.extend(model.sample(rng, spec.bodies, model.total_mass(), 1.0, 1.0));
+        }
+
+        recentre(&mut particles);
+        project(&particles)
+    }
+}
+
+// Modified Bessel functions, Abramowitz & Stegun 9.8.1 - 9.8.8
+
+fn bessel_i0(x: f64) -> f64 {
+    if x <= 3.75 {
+        let t = (x / 3.75).powi(2);
+        1.0 + t * (3.5156229 + t * (3.0899424 + t * (1.2067492 + t * (0.2659732 + t * (0.0360768 + t * 0.0045813)))))
+    } else {
+        let t = 3.75 / x;
+        x.exp() / x.sqrt()
+            * (0.39894228 + t * (0.01328592 + t * (0.00225319 + t * (-0.00157565 + t * (0.00916281
+                + t * (-0.02057706 + t * (0.02635537 + t * (-0.01647633 + t * 0.00392377))))))))
+    }
+}
+
+fn bessel_i1(x: f64) -> f64 {
+    if x <= 3.75 {
+        let t = (x / 3.75).powi(2);
+        x * (0.5 + t * (0.87890594 + t * (0.51498869 + t * (0.15084934 + t * (0.02658733 + t * (0.00301532 + t * 0.00032411))))))
+    } else {
+        let t = 3.75 / x;
+        x.exp() / x.sqrt()
+            * (0.39894228 + t * (-0.03988024 + t * (-0.00362018 + t * (0.00163801 + t * (-0.01031555
+                + t * (0.02282967 + t * (-0.02895312 + t * (0.01787654 - t * 0.00420059))))))))
+    }
+}
+
+fn bessel_k0(x: f64) -> f64 {
+    if x <= 2.0 {
+        let t = x * x / 4.0;
+        -(x / 2.0).ln() * bessel_i0(x)
+            + (-0.57721566 + t * (0.42278420 + t * (0.23069756 + t * (0.03488590 + t * (0.00262698 + t * (0.00010750 + t * 0.0000074))))))
+    } else {
+        let t = 2.0 / x;
+        (-x).exp() / x.sqrt()
+            * (1.25331414 + t * (-0.07832358 + t * (0.02189568 + t * (-0.01062446 + t * (0.00587872 + t * (-0.00251540 + t * 0.00053208))))))
+    }
+}
+
+fn bessel_k1(x: f64) -> f64 {
+    if x <= 2.0 {
+        let t = x * x / 4.0;
+        (x / 2.0).ln() * bessel_i1(x)
+            + (1.0 / x) * (1.0 + t * (0.15443144 + t * (-0.67278579 + t * (-0.18156897 + t * (-0.01919402 + t * (-0.00110404 - t * 0.00004686))))))
+    } else {
+        let t = 2.0 / x;
+        (-x).exp() / x.sqrt()
+            * (1.25331414 + t * (0.23498619 + t * (-0.03655620 + t * (0.01504268 + t * (-0.00780353 + t * (0.00325614 - t * 0.00068245))))))
+    }
+}
@@ -0,0 +1,114 @@
+//! Stellar initial mass functions, in solar masses.
+//!
+//! Each IMF is tabulated as `dN/d ln m` on a log grid between the mass bounds and sampled by
+//! inverting its cumulative distribution. Masses are drawn independently of position, so
+//! `assign_masses` can be applied to the output of any generator.
+use crate::nbody::initial_conditions::interp;
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+
+const TABLE_POINTS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitialMassFunction {
+    /// Single power law `dN/dm ∝ m^-2.35` (Salpeter 1955).
+    Salpeter,
+    /// Broken power law with slopes 0.3, 1.3 and 2.3, breaks at 0.08 and 0.5 (Kroupa 2001).
+    Kroupa,
+    /// Lognormal below 1 with peak 0.079 and width 0.69 dex, `m^-2.3` above (Chabrier 2003).
+    Chabrier,
+}
+
+impl InitialMassFunction {
+    /// Unnormalised `dN/d ln m`.
+    fn log_density(&self, m: f64) -> f64 {
+        match self {
+            Self::Salpeter => m.powf(-1.35),
+            Self::Kroupa => {
+                let dn_dm = if m < 0.08 {
+                    (m / 0.08).powf(-0.3) * (0.08f64 / 0.5).powf(-1.3)
+                } else if m < 0.5 {
+                    (m / 0.5).powf(-1.3)
+                } else {
+                    (m / 0.5).powf(-2.3)
+                };
+                m * dn_dm
+            }
+            Self::Chabrier => {
+                let lognormal = |m: f64| (-(m.log10() - 0.079f64.log10()).powi(2) / (2.0 * 0.69 * 0.69)).exp();
+                if m <= 1.0 { lognormal(m) } else { lognormal(1.0) * m.powf(-1.3) }
+            }
+        }
+    }
+}
+
+/// An IMF truncated to `[min_mass, max_mass]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MassSpectrum {
+    imf: InitialMassFunction,
+    min_mass: f64,
+    max_mass: f64,
+    log_masses: Vec<f64>,
+    cdf: Vec<f64>,
+}
+
+impl MassSpectrum {
+    pub fn new(imf: InitialMassFunction, min_mass: f64, max_mass: f64) -> Self {
+        assert!(0.0 < min_mass && min_mass < max_mass, "mass bounds must satisfy 0 < min < max");
+
+        let step = (max_mass / min_mass).ln() / (TABLE_POINTS - 1) as f64;
+        let log_masses: Vec<f64> = (0..TABLE_POINTS).map(|k| min_mass.ln() + k as f64 * step).collect();
+        let density: Vec<f64> = log_masses.iter().map(|&lm| imf.log_density(lm.exp())).collect();
+        let mut cdf = vec![0.0; TABLE_POINTS];
+        for k in 1..TABLE_POINTS {
+            cdf[k] = cdf[k - 1] + 0.5 * (density[k - 1] + density[k]) * step;
+        }
+        let total = cdf[TABLE_POINTS - 1];
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        Self { imf, min_mass, max_mass, log_masses, cdf }
+    }
+
+    #[inline]
+    pub fn imf(&self) -> InitialMassFunction {
+        self.imf
+    }
+
+    #[inline]
+    pub fn bounds(&self) -> (f64, f64) {
+        (self.min_mass, self.max_mass)
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let u: f64 = rng.random_range(0.0..1.0);
+        interp(&self.cdf, &self.log_masses, u).exp().clamp(self.min_mass, self.max_mass)
+    }
+
+    pub fn sample_masses<R: Rng + ?Sized>(&self, rng: &mut R, n: usize) -> Vec<f64> {
+        (0..n).map(|_| self.sample(rng)).collect()
+    }
+
+    /// Expected mass of one star.
+    pub fn mean_mass(&self) -> f64 {
+        let (mut number, mut mass) = (0.0, 0.0);
+        for k in 1..TABLE_POINTS {
+            let dn = self.cdf[k] - self.cdf[k - 1];
+            number += dn;
+            mass += dn * (0.5 * (self.log_masses[k - 1] + self.log_masses[k])).exp();
+        }
+        mass / number
+    }
+}
+
+/// Draws a mass for every body from `spectrum`, then rescales them so the total mass stays
+/// what it was. Relative masses follow the IMF while positions, velocities and therefore the
+/// generator's equilibrium are left as they are. Use `MassSpectrum::sample_masses` for the
+/// unscaled masses in solar units.
+pub fn assign_masses<R: Rng + ?Sized>(rng: &mut R, bodies: &mut [Body], spectrum: &MassSpectrum) {
+    let total: f64 = bodies.iter().map(|b| b.mass as f64).sum();
+    let masses = spectrum.sample_masses(rng, bodies.len());
+    let drawn: f64 = masses.iter().sum();
+    for (body, mass) in bodies.iter_mut().zip(masses) {
+        body.mass = (mass * total / drawn) as f32;
+    }
+}
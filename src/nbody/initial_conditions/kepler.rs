@@ -0,0 +1,283 @@
+//! Keplerian orbital elements and a builder for planetary systems.
+//!
+//! Elements describe the orbit of a body relative to a central body with
+//! `mu = G (m_central + m_body)`. Angles are in radians; hyperbolic orbits have a negative
+//! semi-major axis. Parabolic orbits (`e = 1`) have an infinite one instead, are sized by
+//! `pericentre_distance` and use Barker's mean anomaly `tan(ν/2) + tan³(ν/2) / 3`. States
+//! are 3D and projected onto the simulation plane like the other generators, so keep
+//! inclinations small for systems that should look face-on.
+use crate::nbody::initial_conditions::{project, recentre, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+use std::f64::consts::TAU;
+
+/// Below this, eccentricity and node vector count as zero.
+const TOLERANCE: f64 = 1e-10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly: f64,
+    /// Size of a parabolic orbit; unused for other eccentricities.
+    pub pericentre_distance: f64,
+}
+
+impl OrbitalElements {
+    /// Circular orbit in the simulation plane, starting at `phase` from the +x axis.
+    pub fn circular(radius: f64, phase: f64) -> Self {
+        Self { semi_major_axis: radius, mean_anomaly: phase, ..Self::default() }
+    }
+
+    /// Parabolic orbit in the simulation plane with pericentre on the +x axis.
+    pub fn parabolic(pericentre_distance: f64, mean_anomaly: f64) -> Self {
+        Self {
+            semi_major_axis: f64::INFINITY,
+            eccentricity: 1.0,
+            mean_anomaly,
+            pericentre_distance,
+            ..Self::default()
+        }
+    }
+
+    /// Elements of the relative orbit with the given position and velocity.
+    pub fn from_state(position: [f64; 3], velocity: [f64; 3], mu: f64) -> Self {
+        let r = norm(position);
+        let h = cross(position, velocity);
+        let h_hat = scale(h, 1.0 / norm(h));
+        let v_cross_h = cross(velocity, h);
+        let e_vec: [f64; 3] = std::array::from_fn(|k| v_cross_h[k] / mu - position[k] / r);
+        let mut e = norm(e_vec);
+        let parabolic = (e - 1.0).abs() < TOLERANCE;
+        let (a, pericentre_distance) = if parabolic {
+            e = 1.0;
+            (f64::INFINITY, dot(h, h) / (2.0 * mu))
+        } else {
+            (1.0 / (2.0 / r - dot(velocity, velocity) / mu), 0.0)
+        };
+
+        let node = [-h[1], h[0], 0.0];
+        let n_hat = if norm(node) > TOLERANCE * norm(h) { scale(node, 1.0 / norm(node)) } else { [1.0, 0.0, 0.0] };
+        let q_hat = cross(h_hat, n_hat);
+        let p_hat = if e > TOLERANCE { scale(e_vec, 1.0 / e) } else { n_hat };
+        let r_hat = scale(position, 1.0 / r);
+        let nu = dot(r_hat, cross(h_hat, p_hat)).atan2(dot(r_hat, p_hat));
+
+        let mean_anomaly = if parabolic {
+            let d = (nu / 2.0).tan();
+            d + d * d * d / 3.0
+        } else if e < 1.0 {
+            let big_e = 2.0 * (((1.0 - e) / (1.0 + e)).sqrt() * (nu / 2.0).tan()).atan();
+            big_e - e * big_e.sin()
+        } else {
+            let big_h = 2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * (nu / 2.0).tan()).atanh();
+            e * big_h.sinh() - big_h
+        };
+
+        Self {
+            semi_major_axis: a,
+            eccentricity: e,
+            inclination: h_hat[2].clamp(-1.0, 1.0).acos(),
+            longitude_of_ascending_node: n_hat[1].atan2(n_hat[0]).rem_euclid(TAU),
+            argument_of_periapsis: dot(p_hat, q_hat).atan2(dot(p_hat, n_hat)).rem_euclid(TAU),
+            mean_anomaly: if e < 1.0 { mean_anomaly.rem_euclid(TAU) } else { mean_anomaly },
+            pericentre_distance,
+        }
+    }
+
+    /// Elements of `body` around `central`, from their (planar) states.
+    pub fn from_bodies(body: &Body, central: &Body, g_constant: f64) -> Self {
+        let position = [
+            (body.position[0] - central.position[0]) as f64,
+            (body.position[1] - central.position[1]) as f64,
+            0.0,
+        ];
+        let velocity = [
+            (body.velocity[0] - central.velocity[0]) as f64,
+            (body.velocity[1] - central.velocity[1]) as f64,
+            0.0,
+        ];
+        Self::from_state(position, velocity, g_constant * (body.mass + central.mass) as f64)
+    }
+
+    /// Relative position and velocity on the orbit.
+    pub fn to_state(&self, mu: f64) -> ([f64; 3], [f64; 3]) {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let m = self.mean_anomaly;
+
+        // Perifocal frame, pericentre on the x axis
+        let (position, velocity) = if e < 1.0 {
+            let big_e = solve_kepler(m, e);
+            let (sin_e, cos_e) = big_e.sin_cos();
+            let r = a * (1.0 - e * cos_e);
+            let b = (1.0 - e * e).sqrt();
+            let v = (mu * a).sqrt() / r;
+            ([a * (cos_e - e), a * b * sin_e, 0.0], [-v * sin_e, v * b * cos_e, 0.0])
+        } else if e == 1.0 {
+            let a = self.pericentre_distance;
+            assert!(a > 0.0, "parabolic orbits need a positive pericentre distance");
+            let d = solve_barker(m);
+            let v = (2.0 * mu / a).sqrt() / (1.0 + d * d);
+            ([a * (1.0 - d * d), 2.0 * a * d, 0.0], [-v * d, v, 0.0])
+        } else {
+            assert!(a < 0.0, "hyperbolic orbits need a negative semi-major axis");
+            let a = -a;
+            let big_h = solve_hyperbolic_kepler(m, e);
+            let (sinh_h, cosh_h) = (big_h.sinh(), big_h.cosh());
+            let r = a * (e * cosh_h - 1.0);
+            let b = (e * e - 1.0).sqrt();
+            let v = (mu * a).sqrt() / r;
+            ([a * (e - cosh_h), a * b * sinh_h, 0.0], [-v * sinh_h, v * b * cosh_h, 0.0])
+        };
+
+        let rotate = |p: [f64; 3]| {
+            let p = rotate_z(p, self.argument_of_periapsis);
+            let p = rotate_x(p, self.inclination);
+            rotate_z(p, self.longitude_of_ascending_node)
+        };
+        (rotate(position), rotate(velocity))
+    }
+
+    /// Orbital period, infinite for unbound orbits.
+    pub fn period(&self, mu: f64) -> f64 {
+        if self.eccentricity < 1.0 {
+            TAU * (self.semi_major_axis.powi(3) / mu).sqrt()
+        } else {
+            f64::INFINITY
+        }
+    }
+}
+
+/// Places planets around a central body; `build` returns the central body first, then the
+/// planets in insertion order, in the barycentric frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanetarySystemBuilder {
+    central_mass: f64,
+    planets: Vec<(f64, OrbitalElements)>,
+    g_constant: f64,
+}
+
+impl PlanetarySystemBuilder {
+    pub fn new(central_mass: f64) -> Self {
+        Self { central_mass, planets: Vec::new(), g_constant: 1.0 }
+    }
+
+    pub fn planet(mut self, mass: f64, elements: OrbitalElements) -> Self {
+        self.planets.push((mass, elements));
+        self
+    }
+
+    /// Must match the `g_constant` the bodies are simulated with.
+    pub fn g_constant(mut self, g_constant: f64) -> Self {
+        self.g_constant = g_constant;
+        self
+    }
+
+    /// Sun and the eight planets from the J2000 mean elements of Standish's "Keplerian Elements
+    /// for Approximate Positions of the Major Planets" (Earth is the Earth-Moon barycentre).
+    /// Units are AU, years and solar masses, so `g_constant` is 4π².
+    pub fn solar_system() -> Self {
+        let g_constant = TAU * TAU;
+        SOLAR_SYSTEM.iter().fold(Self::new(1.0).g_constant(g_constant), |builder, row| {
+            let &(mass, a, e, i, mean_longitude, perihelion_longitude, node) = row;
+            builder.planet(
+                mass,
+                OrbitalElements {
+                    semi_major_axis: a,
+                    eccentricity: e,
+                    inclination: i.to_radians(),
+                    longitude_of_ascending_node: node.to_radians(),
+                    argument_of_periapsis: (perihelion_longitude - node).to_radians().rem_euclid(TAU),
+                    mean_anomaly: (mean_longitude - perihelion_longitude).to_radians().rem_euclid(TAU),
+                    ..OrbitalElements::default()
+                },
+            )
+        })
+    }
+
+    pub fn build(&self) -> Vec<Body> {
+        let mut particles: Vec<Particle3> = vec![([0.0; 3], [0.0; 3], self.central_mass)];
+        for &(mass, elements) in &self.planets {
+            let (position, velocity) = elements.to_state(self.g_constant * (self.central_mass + mass));
+            particles.push((position, velocity, mass));
+        }
+        recentre(&mut particles);
+        project(&particles)
+    }
+}
+
+/// Mass (solar masses), a (AU), e, i, mean longitude, longitude of perihelion and longitude of
+/// the ascending node (degrees).
+const SOLAR_SYSTEM: [(f64, f64, f64, f64, f64, f64, f64); 8] = [
+    (1.6601e-7, 0.38709927, 0.20563593, 7.00497902, 252.25032350, 77.45779628, 48.33076593),
+    (2.4478e-6, 0.72333566, 0.00677672, 3.39467605, 181.97909950, 131.60246718, 76.67984255),
+    (3.0404e-6, 1.00000261, 0.01671123, -0.00001531, 100.46457166, 102.93768193, 0.0),
+    (3.2272e-7, 1.52371034, 0.09339410, 1.84969142, -4.55343205, -23.94362959, 49.55953891),
+    (9.5479e-4, 5.20288700, 0.04838624, 1.30439695, 34.39644051, 14.72847983, 100.47390909),
+    (2.8589e-4, 9.53667594, 0.05386179, 2.48599187, 49.95424423, 92.59887831, 113.66242448),
+    (4.3662e-5, 19.18916464, 0.04725744, 0.77263783, 313.23810451, 170.95427630, 74.01692503),
+    (5.1514e-5, 30.06992276, 0.00859048, 1.77004347, -55.12002969, 44.96476227, 131.78422574),
+];
+
+/// Eccentric anomaly from `M = E - e sin E` by Newton iteration.
+fn solve_kepler(mean_anomaly: f64, e: f64) -> f64 {
+    let m = mean_anomaly.rem_euclid(TAU);
+    let mut big_e = if e > 0.8 { std::f64::consts::PI } else { m };
+    for _ in 0..50 {
+        let delta = (big_e - e * big_e.sin() - m) / (1.0 - e * big_e.cos());
+        big_e -= delta;
+        if delta.abs() < 1e-14 {
+            break;
+        }
+    }
+    big_e
+}
+
+/// `tan(ν/2)` from Barker's equation `M = D + D³/3`, solved in closed form.
+fn solve_barker(mean_anomaly: f64) -> f64 {
+    let b = 1.5 * mean_anomaly;
+    let cube = (b + (b * b + 1.0).sqrt()).cbrt();
+    cube - 1.0 / cube
+}
+
+/// Hyperbolic anomaly from `M = e sinh H - H` by Newton iteration.
+fn solve_hyperbolic_kepler(mean_anomaly: f64, e: f64) -> f64 {
+    let mut big_h = (2.0 * mean_anomaly / e).asinh();
+    for _ in 0..100 {
+        let delta = (e * big_h.sinh() - big_h - mean_anomaly) / (e * big_h.cosh() - 1.0);
+        big_h -= delta;
+        if delta.abs() < 1e-14 {
+            break;
+        }
+    }
+    big_h
+}
+
+fn rotate_z(p: [f64; 3], angle: f64) -> [f64; 3] {
+    let (s, c) = angle.sin_cos();
+    [c * p[0] - s * p[1], s * p[0] + c * p[1], p[2]]
+}
+
+fn rotate_x(p: [f64; 3], angle: f64) -> [f64; 3] {
+    let (s, c) = angle.sin_cos();
+    [p[0], c * p[1] - s * p[2], s * p[1] + c * p[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn scale(a: [f64; 3], factor: f64) -> [f64; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
@@ -0,0 +1,90 @@
+//! Equilibrium models and other initial conditions, re-exported through `simulator::utils`.
+//!
+//! Spherical models are sampled in 3D and projected onto the simulation plane (z and vz are
+//! dropped) until the simulation itself is three-dimensional. Everything is in N-body units
+//! (G = 1), so use `g_constant: 1.0`.
+pub mod collision;
+pub mod disks;
+pub mod galaxy;
+pub mod imf;
+pub mod kepler;
+pub mod lattice;
+pub mod plummer;
+pub mod profiles;
+pub mod zeldovich;
+
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+
+/// A particle of a 3D model before projection: position, velocity, mass.
+pub(crate) type Particle3 = ([f64; 3], [f64; 3], f64);
+
+pub(crate) fn isotropic_vector<R: Rng + ?Sized>(rng: &mut R, length: f64) -> [f64; 3] {
+    let z: f64 = rng.random_range(-1.0..=1.0);
+    let phi: f64 = rng.random_range(0.0..std::f64::consts::TAU);
+    let s = (1.0 - z * z).sqrt();
+    [length * s * phi.cos(), length * s * phi.sin(), length * z]
+}
+
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    // Box-Muller
+    let u1: f64 = rng.random_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.random_range(0.0..std::f64::consts::TAU);
+    (-2.0 * u1.ln()).sqrt() * u2.cos()
+}
+
+/// Linear interpolation in an ascending table, clamped at both ends.
+pub(crate) fn interp(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    let last = xs.len() - 1;
+    if x <= xs[0] {
+        return ys[0];
+    }
+    if x >= xs[last] {
+        return ys[last];
+    }
+    let i = xs.partition_point(|&v| v <= x).clamp(1, last);
+    let t = (x - xs[i - 1]) / (xs[i] - xs[i - 1]);
+    ys[i - 1] + t * (ys[i] - ys[i - 1])
+}
+
+/// Shifts the particles into their centre-of-mass frame.
+pub(crate) fn recentre(particles: &mut [Particle3]) {
+    let total: f64 = particles.iter().map(|p| p.2).sum();
+    if total == 0.0 {
+        return;
+    }
+    let mut com = [0.0f64; 6];
+    for (x, v, m) in particles.iter() {
+        for k in 0..3 {
+            com[k] += m * x[k] / total;
+            com[k + 3] += m * v[k] / total;
+        }
+    }
+    for (x, v, _) in particles.iter_mut() {
+        for k in 0..3 {
+            x[k] -= com[k];
+            v[k] -= com[k + 3];
+        }
+    }
+}
+
+/// Lifts bodies into the 3D model space with z = vz = 0.
+pub(crate) fn lift(bodies: &[Body]) -> Vec<Particle3> {
+    bodies
+        .iter()
+        .map(|b| {
+            (
+                [b.position[0] as f64, b.position[1] as f64, 0.0],
+                [b.velocity[0] as f64, b.velocity[1] as f64, 0.0],
+                b.mass as f64,
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn project(particles: &[Particle3]) -> Vec<Body> {
+    particles
+        .iter()
+        .map(|(x, v, m)| Body::new([x[0] as f32, x[1] as f32], [v[0] as f32, v[1] as f32], *m as f32))
+        .collect()
+}
@@ -0,0 +1,51 @@
+//! Plummer sphere after Aarseth, Hénon & Wielen (1974), in Hénon units (G = M = 1, E = -1/4).
+use crate::nbody::initial_conditions::{isotropic_vector, project, recentre, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Radii beyond the sphere holding this mass fraction are redrawn.
+const MASS_CUTOFF: f64 = 0.999;
+
+/// Samples the 3D Plummer model with equal masses `1 / n`, recentred on its centre of mass.
+pub(crate) fn sample_plummer<R: Rng + ?Sized>(rng: &mut R, n: usize) -> Vec<Particle3> {
+    // Structural units (scale radius a = 1) are rescaled to Hénon units at the end
+    let length_scale = 3.0 * PI / 16.0;
+    let velocity_scale = 1.0 / length_scale.sqrt();
+    let mass = 1.0 / n as f64;
+
+    let mut particles: Vec<Particle3> = (0..n)
+        .map(|_| {
+            // Invert the cumulative mass M(r) = r³ / (1 + r²)^(3/2)
+            let x: f64 = rng.random_range(f64::EPSILON..MASS_CUTOFF);
+            let r = 1.0 / (x.powf(-2.0 / 3.0) - 1.0).sqrt();
+
+            // Speed in units of the local escape speed from g(q) = q² (1 - q²)^(7/2),
+            // whose maximum is just below 0.1
+            let escape_speed = std::f64::consts::SQRT_2 * (1.0 + r * r).powf(-0.25);
+            let q = loop {
+                let q: f64 = rng.random_range(0.0..1.0);
+                let g: f64 = rng.random_range(0.0..0.1);
+                if g < q * q * (1.0 - q * q).powf(3.5) {
+                    break q;
+                }
+            };
+
+            (
+                isotropic_vector(rng, r * length_scale),
+                isotropic_vector(rng, q * escape_speed * velocity_scale),
+                mass,
+            )
+        })
+        .collect();
+
+    recentre(&mut particles);
+    particles
+}
+
+/// Plummer sphere projected onto the simulation plane. The 3D model is in virial equilibrium;
+/// its projection is the standard test cluster as seen face-on. A true 3D variant will follow
+/// once the simulation supports a third dimension.
+pub fn generate_plummer_projected<R: Rng + ?Sized>(rng: &mut R, n: usize) -> Vec<Body> {
+    project(&sample_plummer(rng, n))
+}
@@ -0,0 +1,325 @@
+//! King, Hernquist and NFW equilibrium spheres.
+//!
+//! All three share one tabulated model: radius, enclosed mass and relative potential on a
+//! log-spaced grid. Positions come from inverting the enclosed mass, speeds are drawn by
+//! rejection from `v² f(Ψ - v²/2)`. King has an analytic distribution function and needs the
+//! Poisson equation solved for its potential; Hernquist and NFW get theirs numerically from
+//! Eddington's formula.
+use crate::nbody::initial_conditions::{interp, isotropic_vector, project, recentre, Particle3};
+use crate::nbody::shader_types::nbody::Body;
+use crate::nbody::special::erf;
+use rand::Rng;
+use std::f64::consts::PI;
+
+const TABLE_POINTS: usize = 2000;
+const EDDINGTON_STEPS: usize = 200;
+const SPEED_SCAN_POINTS: usize = 64;
+
+pub(crate) struct SphericalModel {
+    radii: Vec<f64>,
+    enclosed_mass: Vec<f64>,
+    /// Relative potential Ψ = -Φ, zero at the outermost radius.
+    psi: Vec<f64>,
+    df: Box<dyn Fn(f64) -> f64>,
+}
+
+impl SphericalModel {
+    /// Builds the model for a density profile (G = 1) truncated at `r_max`, with the
+    /// distribution function from Eddington's formula.
+    pub(crate) fn from_density(density: impl Fn(f64) -> f64, r_min: f64, r_max: f64) -> Self {
+        let log_step = (r_max / r_min).ln() / (TABLE_POINTS - 1) as f64;
+        let radii: Vec<f64> = (0..TABLE_POINTS).map(|k| r_min * (k as f64 * log_step).exp()).collect();
+        let rho: Vec<f64> = radii.iter().map(|&r| density(r)).collect();
+
+        // dM = 4π r² ρ dr and the outer potential term 4π r ρ dr, with dr = r d(ln r)
+        let mut enclosed_mass = vec![4.0 / 3.0 * PI * r_min.powi(3) * rho[0]; TABLE_POINTS];
+        for k in 1..TABLE_POINTS {
+            let shell = 0.5 * log_step * 4.0 * PI
+                * (radii[k].powi(3) * rho[k] + radii[k - 1].powi(3) * rho[k - 1]);
+            enclosed_mass[k] = enclosed_mass[k - 1] + shell;
+        }
+        let mut outer = vec![0.0; TABLE_POINTS];
+        for k in (0..TABLE_POINTS - 1).rev() {
+            outer[k] = outer[k + 1]
+                + 0.5 * log_step * 4.0 * PI * (radii[k].powi(2) * rho[k] + radii[k + 1].powi(2) * rho[k + 1]);
+        }
+        let edge = enclosed_mass[TABLE_POINTS - 1] / r_max;
+        let psi: Vec<f64> = (0..TABLE_POINTS)
+            .map(|k| enclosed_mass[k] / radii[k] + outer[k] - edge)
+            .collect();
+
+        // Ascending in Ψ for interpolation
+        let psi_asc: Vec<f64> = psi.iter().rev().copied().collect();
+        let rho_asc: Vec<f64> = rho.iter().rev().copied().collect();
+        let mut drho_dpsi = vec![0.0; TABLE_POINTS];
+        for k in 1..TABLE_POINTS - 1 {
+            drho_dpsi[k] = (rho_asc[k + 1] - rho_asc[k - 1]) / (psi_asc[k + 1] - psi_asc[k - 1]);
+        }
+        drho_dpsi[0] = drho_dpsi[1];
+        drho_dpsi[TABLE_POINTS - 1] = drho_dpsi[TABLE_POINTS - 2];
+
+        // f(E) = 1/(√8 π²) d/dE ∫_0^E dρ/dΨ dΨ / √(E - Ψ), with Ψ = E - t² to remove the singularity
+        let integral: Vec<f64> = psi_asc
+            .iter()
+            .map(|&energy| {
+                let t_max = energy.max(0.0).sqrt();
+                let dt = t_max / EDDINGTON_STEPS as f64;
+                (0..=EDDINGTON_STEPS)
+                    .map(|s| {
+                        let t = s as f64 * dt;
+                        let weight = if s == 0 || s == EDDINGTON_STEPS { 0.5 } else { 1.0 };
+                        weight * 2.0 * interp(&psi_asc, &drho_dpsi, energy - t * t) * dt
+                    })
+                    .sum()
+            })
+            .collect();
+        let mut f = vec![0.0; TABLE_POINTS];
+        for k in 1..TABLE_POINTS - 1 {
+            let derivative = (integral[k + 1] - integral[k - 1]) / (psi_asc[k + 1] - psi_asc[k - 1]);
+            f[k] = (derivative / (8f64.sqrt() * PI * PI)).max(0.0);
+        }
+        f[TABLE_POINTS - 1] = f[TABLE_POINTS - 2];
+
+        Self {
+            radii,
+            enclosed_mass,
+            psi,
+            df: Box::new(move |energy| interp(&psi_asc, &f, energy)),
+        }
+    }
+
+    pub(crate) fn total_mass(&self) -> f64 {
+        self.enclosed_mass[self.enclosed_mass.len() - 1]
+    }
+
+    pub(crate) fn enclosed_mass_at(&self, r: f64) -> f64 {
+        interp(&self.radii, &self.enclosed_mass, r)
+    }
+
+    /// Potential energy -∫ M(r)/r dM of the tabulated model.
+    pub(crate) fn potential_energy(&self) -> f64 {
+        (1..self.radii.len())
+            .map(|k| {
+                let dm = self.enclosed_mass[k] - self.enclosed_mass[k - 1];
+                let m = 0.5 * (self.enclosed_mass[k] + self.enclosed_mass[k - 1]);
+                let r = 0.5 * (self.radii[k] + self.radii[k - 1]);
+                -m / r * dm
+            })
+            .sum()
+    }
+
+    fn sample_speed<R: Rng + ?Sized>(&self, rng: &mut R, psi: f64) -> f64 {
+        let v_max = (2.0 * psi.max(0.0)).sqrt();
+        let density = |v: f64| v * v * (self.df)(psi - 0.5 * v * v);
+
+        let peak = (1..=SPEED_SCAN_POINTS)
+            .map(|i| density(v_max * i as f64 / (SPEED_SCAN_POINTS + 1) as f64))
+            .fold(0.0, f64::max);
+        if peak <= 0.0 {
+            return 0.0;
+        }
+        loop {
+            let v = rng.random_range(0.0..=v_max);
+            if rng.random_range(0.0..1.2 * peak) < density(v) {
+                return v;
+            }
+        }
+    }
+
+    /// `n` particles of mass `total_mass / n`, lengths and speeds multiplied by the given scales.
+    pub(crate) fn sample<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        n: usize,
+        total_mass: f64,
+        length_scale: f64,
+        velocity_scale: f64,
+    ) -> Vec<Particle3> {
+        let m_max = self.total_mass();
+        let mut particles: Vec<Particle3> = (0..n)
+            .map(|_| {
+                let r = interp(&self.enclosed_mass, &self.radii, rng.random_range(0.0..m_max));
+                let psi = interp(&self.radii, &self.psi, r);
+                let v = self.sample_speed(rng, psi);
+                (
+                    isotropic_vector(rng, r * length_scale),
+                    isotropic_vector(rng, v * velocity_scale),
+                    total_mass / n as f64,
+                )
+            })
+            .collect();
+        recentre(&mut particles);
+        particles
+    }
+}
+
+// ========== King ==========
+
+/// Density of the lowered isothermal sphere for σ = 1, up to a constant factor.
+fn king_density(w: f64) -> f64 {
+    if w <= 0.0 {
+        return 0.0;
+    }
+    if w < 0.1 {
+        // Series expansion, the closed form cancels catastrophically near the tidal radius
+        return 2.0 / PI.sqrt() * w.powf(2.5) * (4.0 / 15.0 + 8.0 * w / 105.0 + 16.0 * w * w / 945.0);
+    }
+    w.exp() * erf(w.sqrt()) - (4.0 * w / PI).sqrt() * (1.0 + 2.0 * w / 3.0)
+}
+
+/// Solves Poisson's equation for W(r) outward from the centre until W reaches zero at the
+/// tidal radius, in units G = σ = 1.
+pub(crate) fn king_model(w0: f64) -> SphericalModel {
+    assert!(w0 > 0.0, "King W0 must be positive");
+
+    // State (W, r² dW/dr) integrated with RK4 over ln r
+    let rhs = |r: f64, (w, y): (f64, f64)| (y / r, -4.0 * PI * r.powi(3) * king_density(w));
+    let step = 0.002;
+    let mut r = 1e-4;
+    let rho0 = king_density(w0);
+    let mut state = (w0 - 2.0 * PI / 3.0 * rho0 * r * r, -4.0 * PI / 3.0 * rho0 * r.powi(3));
+
+    let mut radii = vec![r];
+    let mut potential = vec![state.0];
+    let mut enclosed_mass = vec![-state.1];
+    while state.0 > 0.0 {
+        let t = r.ln();
+        let at = |dt: f64, s: (f64, f64)| rhs((t + dt).exp(), s);
+        let k1 = at(0.0, state);
+        let k2 = at(0.5 * step, (state.0 + 0.5 * step * k1.0, state.1 + 0.5 * step * k1.1));
+        let k3 = at(0.5 * step, (state.0 + 0.5 * step * k2.0, state.1 + 0.5 * step * k2.1));
+        let k4 = at(step, (state.0 + step * k3.0, state.1 + step * k3.1));
+        let next = (
+            state.0 + step / 6.0 * (k1.0 + 2.0 * k2.0 + 2.0 * k3.0 + k4.0),
+            state.1 + step / 6.0 * (k1.1 + 2.0 * k2.1 + 2.0 * k3.1 + k4.1),
+        );
+        let r_next = (t + step).exp();
+
+        if next.0 <= 0.0 {
+            // Interpolate the tidal radius where W crosses zero
+            let fraction = state.0 / (state.0 - next.0);
+            radii.push(r + fraction * (r_next - r));
+            potential.push(0.0);
+            enclosed_mass.push(-(state.1 + fraction * (next.1 - state.1)));
+            break;
+        }
+        r = r_next;
+        state = next;
+        radii.push(r);
+        potential.push(state.0);
+        enclosed_mass.push(-state.1);
+    }
+
+    SphericalModel {
+        radii,
+        enclosed_mass,
+        psi: potential,
+        df: Box::new(|energy: f64| if energy > 0.0 { energy.exp() - 1.0 } else { 0.0 }),
+    }
+}
+
+/// log10 of tidal over King radius, r_0 = √(9σ² / (4πGρ_0)).
+pub fn king_concentration(w0: f64) -> f64 {
+    let model = king_model(w0);
+    let king_radius = (9.0 / (4.0 * PI * king_density(w0))).sqrt();
+    (model.radii[model.radii.len() - 1] / king_radius).log10()
+}
+
+pub(crate) fn sample_king<R: Rng + ?Sized>(rng: &mut R, n: usize, w0: f64) -> Vec<Particle3> {
+    let model = king_model(w0);
+
+    // Rescale to Hénon units: M = 1 and, by the virial theorem, E = W/2 = -1/4
+    let mass_scale = 1.0 / model.total_mass();
+    let energy = 0.5 * model.potential_energy() * mass_scale * mass_scale;
+    let length_scale = -4.0 * energy;
+    let velocity_scale = (mass_scale / length_scale).sqrt();
+
+    model.sample(rng, n, 1.0, length_scale, velocity_scale)
+}
+
+/// King (1966) model with central potential depth `w0` (typically 1 to 12), projected onto the
+/// simulation plane, in Hénon units (G = M = 1, E = -1/4).
+pub fn generate_king_projected<R: Rng + ?Sized>(rng: &mut R, n: usize, w0: f64) -> Vec<Body> {
+    project(&sample_king(rng, n, w0))
+}
+
+// ========== Hernquist ==========
+
+pub(crate) fn hernquist_model(mass: f64, scale_radius: f64) -> SphericalModel {
+    let a = scale_radius;
+    SphericalModel::from_density(
+        |r| mass * a / (2.0 * PI * r * (r + a).powi(3)),
+        1e-4 * a,
+        1e3 * a,
+    )
+}
+
+pub(crate) fn sample_hernquist<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    mass: f64,
+    scale_radius: f64,
+) -> Vec<Particle3> {
+    hernquist_model(mass, scale_radius).sample(rng, n, mass, 1.0, 1.0)
+}
+
+/// Hernquist (1990) sphere with total `mass` and `scale_radius` (G = 1), projected onto the
+/// simulation plane. Truncated at 1000 scale radii, which holds 99.8% of the mass.
+pub fn generate_hernquist_projected<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    mass: f64,
+    scale_radius: f64,
+) -> Vec<Body> {
+    project(&sample_hernquist(rng, n, mass, scale_radius))
+}
+
+// ========== NFW ==========
+
+/// NFW profile up to the virial radius `c * r_s`, continued by an exponential cut-off so the
+/// total mass is finite and the model stays in equilibrium (Kazantzidis et al. 2004).
+pub(crate) fn nfw_model(virial_mass: f64, scale_radius: f64, concentration: f64) -> SphericalModel {
+    let c = concentration;
+    let r_vir = c * scale_radius;
+    let r_decay = 0.1 * r_vir;
+    let rho0 = virial_mass / (4.0 * PI * scale_radius.powi(3) * ((1.0 + c).ln() - c / (1.0 + c)));
+    let slope = -(1.0 + 3.0 * c) / (1.0 + c) + r_vir / r_decay;
+
+    SphericalModel::from_density(
+        move |r| {
+            if r <= r_vir {
+                let x = r / scale_radius;
+                rho0 / (x * (1.0 + x).powi(2))
+            } else {
+                rho0 / (c * (1.0 + c).powi(2)) * (r / r_vir).powf(slope) * (-(r - r_vir) / r_decay).exp()
+            }
+        },
+        1e-4 * scale_radius,
+        r_vir + 20.0 * r_decay,
+    )
+}
+
+pub(crate) fn sample_nfw<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    virial_mass: f64,
+    scale_radius: f64,
+    concentration: f64,
+) -> Vec<Particle3> {
+    let model = nfw_model(virial_mass, scale_radius, concentration);
+    let mass = model.total_mass();
+    model.sample(rng, n, mass, 1.0, 1.0)
+}
+
+/// NFW halo with `virial_mass` inside `concentration * scale_radius` (G = 1), projected onto
+/// the simulation plane. The exponential cut-off adds mass beyond the virial radius (about 20%
+/// for `concentration = 10`), so the bodies' total mass is larger than `virial_mass`.
+pub fn generate_nfw_projected<R: Rng + ?Sized>(
+    rng: &mut R,
+    n: usize,
+    virial_mass: f64,
+    scale_radius: f64,
+    concentration: f64,
+) -> Vec<Body> {
+    project(&sample_nfw(rng, n, virial_mass, scale_radius, concentration))
+}
@@ -0,0 +1,96 @@
+//! Zel'dovich-approximation initial conditions in a periodic box.
+//!
+//! A Gaussian random field with power spectrum `P(k)` is drawn on a `grid × grid` mesh by
+//! colouring white noise in Fourier space, `δ_k = w_k √P(k) N / L`. The displacement field
+//! `ψ_k = i k δ_k / k²` (so that `δ = -∇·ψ`) moves one body per cell off its cell centre,
+//! `x = q + ψ(q)`, and the growing mode gives `v = velocity_factor ψ(q)`. In an expanding
+//! background `velocity_factor` is `a H f` at the starting time in the units of the run.
+use crate::nbody::initial_conditions::standard_normal;
+use crate::nbody::shader_types::nbody::Body;
+use rand::Rng;
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::f64::consts::TAU;
+use std::sync::Arc;
+
+/// Bodies of mass `total_mass / grid²` on the periodic box `[-L/2, L/2)²` with
+/// `L = box_size`. `power_spectrum` takes the wavenumber `|k| = 2π / λ` and gives the 2D power
+/// at the starting time; the mean (k = 0) mode is always removed.
+pub fn generate_zeldovich<R: Rng + ?Sized>(
+    rng: &mut R,
+    grid: usize,
+    box_size: f64,
+    total_mass: f64,
+    power_spectrum: impl Fn(f64) -> f64,
+    velocity_factor: f64,
+) -> Vec<Body> {
+    assert!(grid >= 2, "grid needs at least 2 cells per side");
+    let n = grid;
+    let cell = box_size / n as f64;
+
+    let mut delta: Vec<Complex<f64>> = (0..n * n).map(|_| Complex::new(standard_normal(rng), 0.0)).collect();
+    let mut planner = FftPlanner::new();
+    let forward = planner.plan_fft_forward(n);
+    let inverse = planner.plan_fft_inverse(n);
+    fft_2d(&forward, &mut delta, n);
+
+    let wavenumber = |index: usize| {
+        let m = if index <= n / 2 { index as f64 } else { index as f64 - n as f64 };
+        TAU * m / box_size
+    };
+    let nyquist = n / 2;
+    let mut psi_x = vec![Complex::new(0.0, 0.0); n * n];
+    let mut psi_y = vec![Complex::new(0.0, 0.0); n * n];
+    for row in 0..n {
+        for column in 0..n {
+            // The Nyquist modes have no partner of opposite k on an even grid, so ψ there
+            // would not be real
+            if (row == 0 && column == 0) || (n.is_multiple_of(2) && (row == nyquist || column == nyquist)) {
+                continue;
+            }
+            let (kx, ky) = (wavenumber(column), wavenumber(row));
+            let k2 = kx * kx + ky * ky;
+            let index = row * n + column;
+            let delta_k = delta[index] * (power_spectrum(k2.sqrt()).max(0.0).sqrt() * n as f64 / box_size);
+            let i_delta = Complex::new(0.0, 1.0) * delta_k / k2;
+            psi_x[index] = i_delta * kx;
+            psi_y[index] = i_delta * ky;
+        }
+    }
+    fft_2d(&inverse, &mut psi_x, n);
+    fft_2d(&inverse, &mut psi_y, n);
+
+    let normalisation = 1.0 / (n * n) as f64;
+    let half = 0.5 * box_size;
+    let wrap = |x: f64| (x + half).rem_euclid(box_size) - half;
+    let mass = (total_mass / (n * n) as f64) as f32;
+    (0..n * n)
+        .map(|index| {
+            let (row, column) = (index / n, index % n);
+            let q = [(column as f64 + 0.5) * cell - half, (row as f64 + 0.5) * cell - half];
+            let psi = [psi_x[index].re * normalisation, psi_y[index].re * normalisation];
+            Body::new(
+                [wrap(q[0] + psi[0]) as f32, wrap(q[1] + psi[1]) as f32],
+                [(velocity_factor * psi[0]) as f32, (velocity_factor * psi[1]) as f32],
+                mass,
+            )
+        })
+        .collect()
+}
+
+/// In-place 2D FFT of a row-major `n × n` array, unnormalised.
+fn fft_2d(fft: &Arc<dyn Fft<f64>>, data: &mut [Complex<f64>], n: usize) {
+    for row in data.chunks_exact_mut(n) {
+        fft.process(row);
+    }
+    let mut column = vec![Complex::new(0.0, 0.0); n];
+    for c in 0..n {
+        for r in 0..n {
+            column[r] = data[r * n + c];
+        }
+        fft.process(&mut column);
+        for r in 0..n {
+            data[r * n + c] = column[r];
+        }
+    }
+}
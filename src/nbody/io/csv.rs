@@ -0,0 +1,130 @@
+//! CSV body sets: a header naming the columns `x, y, vx, vy, mass` and optional `id` and
+//! `species`, followed by one body per line. Column order is free, blank lines are skipped.
+//! If an `id` column is present the bodies are returned sorted by id.
+use crate::nbody::io::IoError;
+use crate::nbody::shader_types::nbody::Body;
+use crate::nbody::species::MAX_SPECIES;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+const REQUIRED_COLUMNS: [&str; 5] = ["x", "y", "vx", "vy", "mass"];
+
+pub fn read_csv<R: BufRead>(reader: R) -> Result<Vec<Body>, IoError> {
+    let mut lines = reader.lines().enumerate();
+
+    // Header: first non-empty line
+    let (header_line, header) = loop {
+        match lines.next() {
+            Some((i, line)) => {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    break (i + 1, line);
+                }
+            }
+            None => return Ok(Vec::new()),
+        }
+    };
+
+    let names: Vec<String> = header.split(',').map(|c| c.trim().to_lowercase()).collect();
+    let mut columns = [0usize; 5];
+    for (slot, required) in columns.iter_mut().zip(REQUIRED_COLUMNS) {
+        *slot = names.iter().position(|n| n == required).ok_or_else(|| IoError::Parse {
+            line: header_line,
+            message: format!("missing column '{}' in header", required),
+        })?;
+    }
+    let id_column = names.iter().position(|n| n == "id");
+    let species_column = names.iter().position(|n| n == "species");
+
+    let mut records: Vec<(Option<u64>, usize, Body)> = Vec::new();
+    for (i, line) in lines {
+        let line = line?;
+        let line_no = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() != names.len() {
+            return Err(IoError::Parse {
+                line: line_no,
+                message: format!("expected {} fields, found {}", names.len(), fields.len()),
+            });
+        }
+
+        let mut values = [0.0f32; 5];
+        for ((value, &column), name) in values.iter_mut().zip(&columns).zip(REQUIRED_COLUMNS) {
+            *value = fields[column].parse().map_err(|_| IoError::Parse {
+                line: line_no,
+                message: format!("invalid value '{}' for column '{}'", fields[column], name),
+            })?;
+        }
+
+        let id = match id_column {
+            Some(column) => Some(fields[column].parse::<u64>().map_err(|_| IoError::Parse {
+                line: line_no,
+                message: format!("invalid id '{}'", fields[column]),
+            })?),
+            None => None,
+        };
+
+        let species = match species_column {
+            Some(column) => match fields[column].parse::<u32>() {
+                Ok(species) if (species as usize) < MAX_SPECIES => species,
+                _ => {
+                    return Err(IoError::Parse {
+                        line: line_no,
+                        message: format!("invalid species '{}', only {} exist", fields[column], MAX_SPECIES),
+                    })
+                }
+            },
+            None => 0,
+        };
+
+        let [x, y, vx, vy, mass] = values;
+        records.push((id, line_no, Body::new([x, y], [vx, vy], mass).with_species(species)));
+    }
+
+    if id_column.is_some() {
+        records.sort_by_key(|(id, _, _)| *id);
+        for pair in records.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                return Err(IoError::Parse {
+                    line: pair[1].1.max(pair[0].1),
+                    message: format!("duplicate id {}", pair[1].0.unwrap_or_default()),
+                });
+            }
+        }
+    }
+
+    Ok(records.into_iter().map(|(_, _, body)| body).collect())
+}
+
+/// Writes all bodies with a header; the `id` column is the index in `bodies`. A `species`
+/// column is added when any body isn't a star.
+pub fn write_csv<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let with_species = bodies.iter().any(|body| body.species() != 0);
+    writeln!(writer, "x,y,vx,vy,mass,id{}", if with_species { ",species" } else { "" })?;
+    for (id, body) in bodies.iter().enumerate() {
+        write!(
+            writer,
+            "{},{},{},{},{},{}",
+            body.position[0], body.position[1], body.velocity[0], body.velocity[1], body.mass, id
+        )?;
+        if with_species {
+            write!(writer, ",{}", body.species())?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_csv<P: AsRef<Path>>(path: P) -> Result<Vec<Body>, IoError> {
+    read_csv(BufReader::new(File::open(path)?))
+}
+
+pub fn save_csv<P: AsRef<Path>>(path: P, bodies: &[Body]) -> Result<(), IoError> {
+    write_csv(BufWriter::new(File::create(path)?), bodies)
+}
@@ -0,0 +1,454 @@
+//! Gadget-2 binary snapshots (format 1 and the labelled format 2), single file per snapshot.
+//!
+//! The simulation is two-dimensional: on reading only `x, y` and `vx, vy` are kept, on writing
+//! `z` and `vz` are zero. Particle types map to masses through the header mass table or the
+//! `MASS` block. Endianness and single/double precision blocks are detected when reading;
+//! writing always produces little-endian single precision.
+use crate::nbody::io::IoError;
+use crate::nbody::shader_types::nbody::Body;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+pub const GADGET_TYPES: usize = 6;
+const HEADER_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GadgetFormat {
+    /// Plain Fortran records in the fixed order HEAD, POS, VEL, ID, MASS.
+    Format1,
+    /// Every block is preceded by a small record holding its 4-character label.
+    Format2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GadgetHeader {
+    pub npart: [u32; GADGET_TYPES],
+    pub mass_table: [f64; GADGET_TYPES],
+    pub time: f64,
+    pub redshift: f64,
+    pub flag_sfr: i32,
+    pub flag_feedback: i32,
+    pub npart_total: [u32; GADGET_TYPES],
+    pub flag_cooling: i32,
+    pub num_files: i32,
+    pub box_size: f64,
+    pub omega0: f64,
+    pub omega_lambda: f64,
+    pub hubble_param: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GadgetSnapshot {
+    pub header: GadgetHeader,
+    /// Bodies in file order, i.e. grouped by particle type.
+    pub bodies: Vec<Body>,
+    pub ids: Vec<u64>,
+    pub particle_types: Vec<u8>,
+}
+
+impl GadgetSnapshot {
+    /// All bodies as one particle type with ids `0..n`.
+    pub fn from_bodies(bodies: Vec<Body>, particle_type: u8) -> Self {
+        let n = bodies.len();
+        Self {
+            header: GadgetHeader { num_files: 1, ..Default::default() },
+            bodies,
+            ids: (0..n as u64).collect(),
+            particle_types: vec![particle_type; n],
+        }
+    }
+}
+
+// ========== Reading ==========
+
+struct RecordReader<R: Read> {
+    reader: R,
+    big_endian: bool,
+    /// Largest record the snapshot can hold; the header until its particle counts are known.
+    max_record: usize,
+}
+
+impl<R: Read> RecordReader<R> {
+    fn u32_from(&self, bytes: [u8; 4]) -> u32 {
+        if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    }
+
+    fn read_marker(&mut self) -> Result<u32, IoError> {
+        let mut bytes = [0u8; 4];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(self.u32_from(bytes))
+    }
+
+    /// Reads one Fortran record, `None` on a clean end of file.
+    fn read_record(&mut self, block: &str) -> Result<Option<Vec<u8>>, IoError> {
+        let mut bytes = [0u8; 4];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let size = self.u32_from(bytes) as usize;
+        if size > self.max_record {
+            return Err(IoError::Invalid(format!(
+                "{} record claims {} bytes, at most {} expected", block, size, self.max_record
+            )));
+        }
+        // Grows with the bytes actually read, so a lying marker can't force a large allocation
+        let mut data = Vec::new();
+        (&mut self.reader).take(size as u64).read_to_end(&mut data)?;
+        if data.len() != size {
+            return Err(IoError::Invalid(format!("{} record is truncated", block)));
+        }
+        if self.read_marker()? as usize != size {
+            return Err(IoError::Invalid(format!("record markers of {} block don't match", block)));
+        }
+        Ok(Some(data))
+    }
+
+    /// Reads the label record of a format 2 file.
+    fn read_label(&mut self) -> Result<Option<String>, IoError> {
+        let Some(data) = self.read_record("label")? else { return Ok(None) };
+        if data.len() != 8 {
+            return Err(IoError::Invalid(format!("label record has {} bytes instead of 8", data.len())));
+        }
+        Ok(Some(String::from_utf8_lossy(&data[..4]).trim_end().to_string()))
+    }
+}
+
+struct Fields<'a> {
+    data: &'a [u8],
+    offset: usize,
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(&self.data[self.offset..self.offset + N]);
+        self.offset += N;
+        if self.big_endian {
+            bytes.reverse();
+        }
+        bytes
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.take())
+    }
+
+    fn f32(&mut self) -> f32 {
+        f32::from_le_bytes(self.take())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+}
+
+fn parse_header(data: &[u8], big_endian: bool) -> Result<GadgetHeader, IoError> {
+    if data.len() != HEADER_SIZE {
+        return Err(IoError::Invalid(format!("header has {} bytes instead of {}", data.len(), HEADER_SIZE)));
+    }
+    let mut f = Fields { data, offset: 0, big_endian };
+    let mut header = GadgetHeader::default();
+    header.npart.iter_mut().for_each(|v| *v = f.u32());
+    header.mass_table.iter_mut().for_each(|v| *v = f.f64());
+    header.time = f.f64();
+    header.redshift = f.f64();
+    header.flag_sfr = f.i32();
+    header.flag_feedback = f.i32();
+    header.npart_total.iter_mut().for_each(|v| *v = f.u32());
+    header.flag_cooling = f.i32();
+    header.num_files = f.i32();
+    header.box_size = f.f64();
+    header.omega0 = f.f64();
+    header.omega_lambda = f.f64();
+    header.hubble_param = f.f64();
+    Ok(header)
+}
+
+/// Decodes `n` vectors of 3 floats, single or double precision.
+fn parse_vectors(data: &[u8], n: usize, big_endian: bool, block: &str) -> Result<Vec<[f32; 2]>, IoError> {
+    let double = if data.len() == n * 12 {
+        false
+    } else if data.len() == n * 24 {
+        true
+    } else {
+        return Err(IoError::Invalid(format!("{} block has {} bytes for {} particles", block, data.len(), n)));
+    };
+    let mut f = Fields { data, offset: 0, big_endian };
+    Ok((0..n)
+        .map(|_| {
+            let v = if double {
+                [f.f64() as f32, f.f64() as f32, f.f64() as f32]
+            } else {
+                [f.f32(), f.f32(), f.f32()]
+            };
+            [v[0], v[1]]
+        })
+        .collect())
+}
+
+pub fn read_gadget<R: Read>(mut reader: R) -> Result<GadgetSnapshot, IoError> {
+    // The first marker is 256 (format 1) or 8 (format 2), byte-swapped for big-endian files
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first)?;
+    let (format, big_endian) = match (u32::from_le_bytes(first), u32::from_be_bytes(first)) {
+        (256, _) => (GadgetFormat::Format1, false),
+        (8, _) => (GadgetFormat::Format2, false),
+        (_, 256) => (GadgetFormat::Format1, true),
+        (_, 8) => (GadgetFormat::Format2, true),
+        _ => return Err(IoError::Invalid("not a Gadget snapshot".into())),
+    };
+    let mut records = RecordReader { reader: first.as_slice().chain(reader), big_endian, max_record: HEADER_SIZE };
+
+    if format == GadgetFormat::Format2 {
+        match records.read_label()? {
+            Some(label) if label == "HEAD" => {}
+            _ => return Err(IoError::Invalid("format 2 snapshot doesn't start with HEAD".into())),
+        }
+    }
+    let header_data = records
+        .read_record("HEAD")?
+        .ok_or_else(|| IoError::Invalid("missing header".into()))?;
+    let header = parse_header(&header_data, big_endian)?;
+
+    let n: usize = header.npart.iter().map(|&c| c as usize).sum();
+    // Three f64 components per particle is the widest block
+    records.max_record = n.saturating_mul(24).max(8);
+    let mass_block_len: usize = header
+        .npart
+        .iter()
+        .zip(header.mass_table)
+        .filter(|(_, m)| *m == 0.0)
+        .map(|(&c, _)| c as usize)
+        .sum();
+
+    let mut blocks: Vec<(String, Vec<u8>)> = Vec::new();
+    match format {
+        GadgetFormat::Format1 => {
+            for label in ["POS", "VEL", "ID", "MASS"] {
+                if label == "MASS" && mass_block_len == 0 {
+                    break;
+                }
+                let data = records
+                    .read_record(label)?
+                    .ok_or_else(|| IoError::Invalid(format!("missing {} block", label)))?;
+                blocks.push((label.to_string(), data));
+            }
+        }
+        GadgetFormat::Format2 => {
+            while let Some(label) = records.read_label()? {
+                let data = records
+                    .read_record(&label)?
+                    .ok_or_else(|| IoError::Invalid(format!("missing data for {} block", label)))?;
+                blocks.push((label, data));
+            }
+        }
+    }
+    let block = |label: &str| blocks.iter().find(|(l, _)| l == label).map(|(_, d)| d.as_slice());
+
+    let positions = parse_vectors(
+        block("POS").ok_or_else(|| IoError::Invalid("missing POS block".into()))?,
+        n, big_endian, "POS",
+    )?;
+    let velocities = parse_vectors(
+        block("VEL").ok_or_else(|| IoError::Invalid("missing VEL block".into()))?,
+        n, big_endian, "VEL",
+    )?;
+
+    let ids = match block("ID") {
+        Some(data) if data.len() == n * 4 => {
+            let mut f = Fields { data, offset: 0, big_endian };
+            (0..n).map(|_| f.u32() as u64).collect()
+        }
+        Some(data) if data.len() == n * 8 => {
+            let mut f = Fields { data, offset: 0, big_endian };
+            (0..n).map(|_| f.u64()).collect()
+        }
+        Some(data) => {
+            return Err(IoError::Invalid(format!("ID block has {} bytes for {} particles", data.len(), n)));
+        }
+        None => (0..n as u64).collect(),
+    };
+
+    let mut individual_masses = match block("MASS") {
+        Some(data) if data.len() == mass_block_len * 4 => {
+            let mut f = Fields { data, offset: 0, big_endian };
+            (0..mass_block_len).map(|_| f.f32()).collect::<Vec<_>>()
+        }
+        Some(data) if data.len() == mass_block_len * 8 => {
+            let mut f = Fields { data, offset: 0, big_endian };
+            (0..mass_block_len).map(|_| f.f64() as f32).collect()
+        }
+        Some(data) => {
+            return Err(IoError::Invalid(format!(
+                "MASS block has {} bytes for {} particles", data.len(), mass_block_len
+            )));
+        }
+        None if mass_block_len > 0 => return Err(IoError::Invalid("missing MASS block".into())),
+        None => Vec::new(),
+    }
+    .into_iter();
+
+    let mut bodies = Vec::with_capacity(n);
+    let mut particle_types = Vec::with_capacity(n);
+    for (t, &count) in header.npart.iter().enumerate() {
+        for _ in 0..count {
+            let i = bodies.len();
+            let mass = if header.mass_table[t] != 0.0 {
+                header.mass_table[t] as f32
+            } else {
+                individual_masses.next().unwrap_or_default()
+            };
+            bodies.push(Body::new(positions[i], velocities[i], mass));
+            particle_types.push(t as u8);
+        }
+    }
+
+    Ok(GadgetSnapshot { header, bodies, ids, particle_types })
+}
+
+pub fn load_gadget<P: AsRef<Path>>(path: P) -> Result<GadgetSnapshot, IoError> {
+    read_gadget(BufReader::new(File::open(path)?))
+}
+
+// ========== Writing ==========
+
+fn write_record<W: Write>(
+    writer: &mut W,
+    format: GadgetFormat,
+    label: &str,
+    data: &[u8],
+) -> Result<(), IoError> {
+    let size = u32::try_from(data.len())
+        .map_err(|_| IoError::Invalid(format!("{} block exceeds the 4 GiB record limit", label)))?;
+    if format == GadgetFormat::Format2 {
+        let mut tag = [b' '; 4];
+        tag[..label.len()].copy_from_slice(label.as_bytes());
+        writer.write_all(&8u32.to_le_bytes())?;
+        writer.write_all(&tag)?;
+        writer.write_all(&(size + 8).to_le_bytes())?;
+        writer.write_all(&8u32.to_le_bytes())?;
+    }
+    writer.write_all(&size.to_le_bytes())?;
+    writer.write_all(data)?;
+    writer.write_all(&size.to_le_bytes())?;
+    Ok(())
+}
+
+/// Writes the snapshot sorted by particle type. The particle counts and the mass table are
+/// derived from the bodies: a type whose bodies all share one mass goes into the mass table,
+/// any other type gets individual entries in the `MASS` block.
+pub fn write_gadget<W: Write>(
+    mut writer: W,
+    snapshot: &GadgetSnapshot,
+    format: GadgetFormat,
+) -> Result<(), IoError> {
+    let n = snapshot.bodies.len();
+    if snapshot.ids.len() != n || snapshot.particle_types.len() != n {
+        return Err(IoError::Invalid("bodies, ids and particle types differ in length".into()));
+    }
+    if let Some(&t) = snapshot.particle_types.iter().find(|&&t| t as usize >= GADGET_TYPES) {
+        return Err(IoError::Invalid(format!("particle type {} out of range", t)));
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by_key(|&i| snapshot.particle_types[i]);
+
+    let mut header = snapshot.header;
+    header.npart = [0; GADGET_TYPES];
+    header.mass_table = [0.0; GADGET_TYPES];
+    for t in 0..GADGET_TYPES {
+        let masses: Vec<f32> = order
+            .iter()
+            .filter(|&&i| snapshot.particle_types[i] as usize == t)
+            .map(|&i| snapshot.bodies[i].mass)
+            .collect();
+        header.npart[t] = masses.len() as u32;
+        if let Some(&first) = masses.first()
+            && first != 0.0
+            && masses.iter().all(|&m| m == first)
+        {
+            header.mass_table[t] = first as f64;
+        }
+    }
+    header.npart_total = header.npart;
+
+    let mut data = Vec::with_capacity(HEADER_SIZE);
+    header.npart.iter().for_each(|v| data.extend_from_slice(&v.to_le_bytes()));
+    header.mass_table.iter().for_each(|v| data.extend_from_slice(&v.to_le_bytes()));
+    data.extend_from_slice(&header.time.to_le_bytes());
+    data.extend_from_slice(&header.redshift.to_le_bytes());
+    data.extend_from_slice(&header.flag_sfr.to_le_bytes());
+    data.extend_from_slice(&header.flag_feedback.to_le_bytes());
+    header.npart_total.iter().for_each(|v| data.extend_from_slice(&v.to_le_bytes()));
+    data.extend_from_slice(&header.flag_cooling.to_le_bytes());
+    data.extend_from_slice(&header.num_files.to_le_bytes());
+    data.extend_from_slice(&header.box_size.to_le_bytes());
+    data.extend_from_slice(&header.omega0.to_le_bytes());
+    data.extend_from_slice(&header.omega_lambda.to_le_bytes());
+    data.extend_from_slice(&header.hubble_param.to_le_bytes());
+    data.resize(HEADER_SIZE, 0);
+    write_record(&mut writer, format, "HEAD", &data)?;
+
+    let mut data = Vec::with_capacity(n * 12);
+    for &i in &order {
+        let p = snapshot.bodies[i].position;
+        for v in [p[0], p[1], 0.0] {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    write_record(&mut writer, format, "POS", &data)?;
+
+    data.clear();
+    for &i in &order {
+        let v = snapshot.bodies[i].velocity;
+        for c in [v[0], v[1], 0.0] {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+    }
+    write_record(&mut writer, format, "VEL", &data)?;
+
+    data.clear();
+    let wide_ids = snapshot.ids.iter().any(|&id| id > u32::MAX as u64);
+    for &i in &order {
+        let id = snapshot.ids[i];
+        if wide_ids {
+            data.extend_from_slice(&id.to_le_bytes());
+        } else {
+            data.extend_from_slice(&(id as u32).to_le_bytes());
+        }
+    }
+    write_record(&mut writer, format, "ID", &data)?;
+
+    data.clear();
+    for &i in &order {
+        if header.mass_table[snapshot.particle_types[i] as usize] == 0.0 {
+            data.extend_from_slice(&snapshot.bodies[i].mass.to_le_bytes());
+        }
+    }
+    if !data.is_empty() {
+        write_record(&mut writer, format, "MASS", &data)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn save_gadget<P: AsRef<Path>>(
+    path: P,
+    snapshot: &GadgetSnapshot,
+    format: GadgetFormat,
+) -> Result<(), IoError> {
+    write_gadget(BufWriter::new(File::create(path)?), snapshot, format)
+}
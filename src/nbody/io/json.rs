@@ -0,0 +1,87 @@
+//! JSON body sets: an array of objects `{"x", "y", "vx", "vy", "mass"}` with optional `"id"`
+//! and `"species"`. If ids are present on every body the result is sorted by id.
+use crate::nbody::io::IoError;
+use crate::nbody::shader_types::nbody::Body;
+use crate::nbody::species::MAX_SPECIES;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BodyRecord {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    mass: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    species: Option<u32>,
+}
+
+pub fn read_json<R: Read>(reader: R) -> Result<Vec<Body>, IoError> {
+    let mut records: Vec<BodyRecord> = serde_json::from_reader(reader).map_err(|err| {
+        if err.is_io() {
+            IoError::Io(err.into())
+        } else {
+            IoError::Parse { line: err.line(), message: err.to_string() }
+        }
+    })?;
+
+    if let Some((i, species)) = records
+        .iter()
+        .enumerate()
+        .find_map(|(i, r)| r.species.filter(|&s| s as usize >= MAX_SPECIES).map(|s| (i, s)))
+    {
+        return Err(IoError::Invalid(format!("body {} has species {}, only {} exist", i, species, MAX_SPECIES)));
+    }
+
+    let with_id = records.iter().filter(|r| r.id.is_some()).count();
+    if with_id > 0 {
+        if with_id != records.len() {
+            return Err(IoError::Invalid("either all bodies or none must have an id".into()));
+        }
+        records.sort_by_key(|r| r.id);
+        if let Some(pair) = records.windows(2).find(|pair| pair[0].id == pair[1].id) {
+            return Err(IoError::Invalid(format!("duplicate id {}", pair[0].id.unwrap_or_default())));
+        }
+    }
+
+    Ok(records
+        .into_iter()
+        .map(|r| Body::new([r.x, r.y], [r.vx, r.vy], r.mass).with_species(r.species.unwrap_or(0)))
+        .collect())
+}
+
+/// Writes a pretty-printed array; `id` is the index in `bodies`, `species` is left out for stars.
+pub fn write_json<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let records: Vec<BodyRecord> = bodies
+        .iter()
+        .enumerate()
+        .map(|(id, body)| BodyRecord {
+            x: body.position[0],
+            y: body.position[1],
+            vx: body.velocity[0],
+            vy: body.velocity[1],
+            mass: body.mass,
+            id: Some(id as u64),
+            species: Some(body.species()).filter(|&s| s != 0),
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(&mut writer, &records).map_err(|err| IoError::Io(err.into()))?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn load_json<P: AsRef<Path>>(path: P) -> Result<Vec<Body>, IoError> {
+    read_json(BufReader::new(File::open(path)?))
+}
+
+pub fn save_json<P: AsRef<Path>>(path: P, bodies: &[Body]) -> Result<(), IoError> {
+    write_json(BufWriter::new(File::create(path)?), bodies)
+}
@@ -0,0 +1,43 @@
+//! Readers and writers for exchanging body sets with other tools.
+pub mod csv;
+pub mod gadget;
+pub mod json;
+pub mod npy;
+pub mod vtk;
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum IoError {
+    /// Underlying read/write failure.
+    Io(std::io::Error),
+    /// Malformed input, `line` is 1-based.
+    Parse { line: usize, message: String },
+    /// Well-formed input that doesn't describe a valid body set.
+    Invalid(String),
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoError::Io(err) => write!(f, "I/O error: {}", err),
+            IoError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            IoError::Invalid(message) => write!(f, "invalid body set: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for IoError {
+    fn from(err: std::io::Error) -> Self {
+        IoError::Io(err)
+    }
+}
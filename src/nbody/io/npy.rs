@@ -0,0 +1,236 @@
+//! NumPy `.npy` arrays and `.npz` snapshot bundles (uncompressed zip, as `numpy.savez` writes).
+//!
+//! Every snapshot holds `positions` and `velocities` as `float32` arrays of shape `(n, 2)`
+//! and `masses` of shape `(n,)`. Arrays written from `SimdAlignedNBodyCore` use Fortran order,
+//! so its SoA columns go to disk unchanged; NumPy loads both layouts identically.
+use crate::nbody::io::IoError;
+use crate::nbody::shader_types::nbody::Body;
+use crate::nbody::simd_alligned_core::SimdAlignedNBodyCore;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const NPY_ALIGNMENT: usize = 64;
+
+/// A float32 array whose data is the concatenation of `chunks`.
+struct NpyArray<'a> {
+    shape: Vec<usize>,
+    fortran_order: bool,
+    chunks: Vec<&'a [f32]>,
+}
+
+impl<'a> NpyArray<'a> {
+    fn row_major(shape: Vec<usize>, data: &'a [f32]) -> Self {
+        Self { shape, fortran_order: false, chunks: vec![data] }
+    }
+
+    fn columns(columns: Vec<&'a [f32]>) -> Self {
+        let n = columns.first().map_or(0, |c| c.len());
+        Self { shape: vec![n, columns.len()], fortran_order: true, chunks: columns }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<(), IoError> {
+        let expected: usize = self.shape.iter().product();
+        let actual: usize = self.chunks.iter().map(|c| c.len()).sum();
+        if expected != actual {
+            return Err(IoError::Invalid(format!(
+                "array of shape {:?} needs {} values, got {}", self.shape, expected, actual
+            )));
+        }
+
+        let shape = match self.shape.as_slice() {
+            [n] => format!("({},)", n),
+            dims => format!("({})", dims.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+        };
+        let mut header = format!(
+            "{{'descr': '<f4', 'fortran_order': {}, 'shape': {}, }}",
+            if self.fortran_order { "True" } else { "False" },
+            shape
+        );
+        // magic (6) + version (2) + header length (2) + header, padded with spaces and '\n'
+        let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+        header.push_str(&" ".repeat((NPY_ALIGNMENT - unpadded % NPY_ALIGNMENT) % NPY_ALIGNMENT));
+        header.push('\n');
+
+        writer.write_all(NPY_MAGIC)?;
+        writer.write_all(&[1, 0])?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        for chunk in &self.chunks {
+            for value in chunk.iter() {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, IoError> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+struct SnapshotArrays {
+    positions: Vec<f32>,
+    velocities: Vec<f32>,
+    masses: Vec<f32>,
+}
+
+impl SnapshotArrays {
+    fn from_bodies(bodies: &[Body]) -> Self {
+        Self {
+            positions: bodies.iter().flat_map(|b| b.position).collect(),
+            velocities: bodies.iter().flat_map(|b| b.velocity).collect(),
+            masses: bodies.iter().map(|b| b.mass).collect(),
+        }
+    }
+
+    fn arrays(&self) -> [(&'static str, NpyArray<'_>); 3] {
+        let n = self.masses.len();
+        [
+            ("positions", NpyArray::row_major(vec![n, 2], &self.positions)),
+            ("velocities", NpyArray::row_major(vec![n, 2], &self.velocities)),
+            ("masses", NpyArray::row_major(vec![n], &self.masses)),
+        ]
+    }
+}
+
+fn core_arrays(core: &SimdAlignedNBodyCore) -> [(&'static str, NpyArray<'_>); 3] {
+    [
+        ("positions", NpyArray::columns(vec![core.position_x(), core.position_y()])),
+        ("velocities", NpyArray::columns(vec![core.velocity_x(), core.velocity_y()])),
+        ("masses", NpyArray::row_major(vec![core.mass().len()], core.mass())),
+    ]
+}
+
+pub fn write_npy_positions<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let positions: Vec<f32> = bodies.iter().flat_map(|b| b.position).collect();
+    NpyArray::row_major(vec![bodies.len(), 2], &positions).write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_npy_velocities<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let velocities: Vec<f32> = bodies.iter().flat_map(|b| b.velocity).collect();
+    NpyArray::row_major(vec![bodies.len(), 2], &velocities).write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_npy_masses<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let masses: Vec<f32> = bodies.iter().map(|b| b.mass).collect();
+    NpyArray::row_major(vec![bodies.len()], &masses).write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes `positions.npy`, `velocities.npy` and `masses.npy` into `dir`.
+pub fn save_npy<P: AsRef<Path>>(dir: P, bodies: &[Body]) -> Result<(), IoError> {
+    let dir = dir.as_ref();
+    let snapshot = SnapshotArrays::from_bodies(bodies);
+    for (name, array) in snapshot.arrays() {
+        let mut writer = BufWriter::new(File::create(dir.join(format!("{}.npy", name)))?);
+        array.write(&mut writer)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+pub fn write_npz<W: Write>(writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let snapshot = SnapshotArrays::from_bodies(bodies);
+    write_zip(writer, &snapshot.arrays())
+}
+
+/// Same bundle as `write_npz`, written straight from the SoA columns.
+pub fn write_npz_columns<W: Write>(writer: W, core: &SimdAlignedNBodyCore) -> Result<(), IoError> {
+    write_zip(writer, &core_arrays(core))
+}
+
+pub fn save_npz<P: AsRef<Path>>(path: P, bodies: &[Body]) -> Result<(), IoError> {
+    write_npz(BufWriter::new(File::create(path)?), bodies)
+}
+
+pub fn save_npz_columns<P: AsRef<Path>>(path: P, core: &SimdAlignedNBodyCore) -> Result<(), IoError> {
+    write_npz_columns(BufWriter::new(File::create(path)?), core)
+}
+
+// ========== Minimal stored (uncompressed) zip writer ==========
+
+const ZIP_VERSION: u16 = 20;
+const DOS_DATE_1980_01_01: u16 = 0x21;
+
+fn write_zip<W: Write>(mut writer: W, arrays: &[(&str, NpyArray<'_>)]) -> Result<(), IoError> {
+    let mut offset = 0u64;
+    let mut central_directory = Vec::new();
+
+    for (name, array) in arrays {
+        let file_name = format!("{}.npy", name);
+        let data = array.to_bytes()?;
+        let size = u32::try_from(data.len())
+            .map_err(|_| IoError::Invalid(format!("{} exceeds the 4 GiB zip limit", file_name)))?;
+        let local_offset = u32::try_from(offset)
+            .map_err(|_| IoError::Invalid("archive exceeds the 4 GiB zip limit".into()))?;
+        let crc = crc32(&data);
+
+        let mut local = Vec::with_capacity(30 + file_name.len());
+        local.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        push_entry_fields(&mut local, crc, size, file_name.len());
+        local.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        local.extend_from_slice(file_name.as_bytes());
+        writer.write_all(&local)?;
+        writer.write_all(&data)?;
+
+        central_directory.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central_directory.extend_from_slice(&ZIP_VERSION.to_le_bytes()); // version made by
+        push_entry_fields(&mut central_directory, crc, size, file_name.len());
+        central_directory.extend_from_slice(&[0u8; 12]); // extra, comment, disk, attributes
+        central_directory.extend_from_slice(&local_offset.to_le_bytes());
+        central_directory.extend_from_slice(file_name.as_bytes());
+
+        offset += (local.len() + data.len()) as u64;
+    }
+
+    let directory_offset = u32::try_from(offset)
+        .map_err(|_| IoError::Invalid("archive exceeds the 4 GiB zip limit".into()))?;
+    let entries = arrays.len() as u16;
+    let mut end = Vec::with_capacity(22);
+    end.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    end.extend_from_slice(&[0u8; 4]); // disk numbers
+    end.extend_from_slice(&entries.to_le_bytes());
+    end.extend_from_slice(&entries.to_le_bytes());
+    end.extend_from_slice(&(central_directory.len() as u32).to_le_bytes());
+    end.extend_from_slice(&directory_offset.to_le_bytes());
+    end.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+    writer.write_all(&central_directory)?;
+    writer.write_all(&end)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Fields shared by local and central headers, from "version needed" to "file name length".
+fn push_entry_fields(out: &mut Vec<u8>, crc: u32, size: u32, name_len: usize) {
+    out.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes()); // flags
+    out.extend_from_slice(&0u16.to_le_bytes()); // method: stored
+    out.extend_from_slice(&0u16.to_le_bytes()); // time
+    out.extend_from_slice(&DOS_DATE_1980_01_01.to_le_bytes());
+    out.extend_from_slice(&crc.to_le_bytes());
+    out.extend_from_slice(&size.to_le_bytes()); // compressed
+    out.extend_from_slice(&size.to_le_bytes()); // uncompressed
+    out.extend_from_slice(&(name_len as u16).to_le_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,135 @@
+//! ParaView output: XML PolyData (`.vtp`) point clouds and `.pvd` time-series indices.
+//! Points are written with `z = 0`, velocity and mass are point attributes.
+use crate::nbody::io::IoError;
+use crate::nbody::shader_types::nbody::Body;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+pub fn write_vtp<W: Write>(mut writer: W, bodies: &[Body]) -> Result<(), IoError> {
+    let n = bodies.len();
+    writeln!(writer, r#"<?xml version="1.0"?>"#)?;
+    writeln!(writer, r#"<VTKFile type="PolyData" version="0.1" byte_order="LittleEndian">"#)?;
+    writeln!(writer, "  <PolyData>")?;
+    writeln!(
+        writer,
+        r#"    <Piece NumberOfPoints="{n}" NumberOfVerts="{n}" NumberOfLines="0" NumberOfStrips="0" NumberOfPolys="0">"#
+    )?;
+
+    writeln!(writer, r#"      <PointData Scalars="mass" Vectors="velocity">"#)?;
+    writeln!(writer, r#"        <DataArray type="Float32" Name="velocity" NumberOfComponents="3" format="ascii">"#)?;
+    for body in bodies {
+        writeln!(writer, "          {} {} 0", body.velocity[0], body.velocity[1])?;
+    }
+    writeln!(writer, "        </DataArray>")?;
+    writeln!(writer, r#"        <DataArray type="Float32" Name="mass" format="ascii">"#)?;
+    for body in bodies {
+        writeln!(writer, "          {}", body.mass)?;
+    }
+    writeln!(writer, "        </DataArray>")?;
+    writeln!(writer, "      </PointData>")?;
+
+    writeln!(writer, "      <Points>")?;
+    writeln!(writer, r#"        <DataArray type="Float32" NumberOfComponents="3" format="ascii">"#)?;
+    for body in bodies {
+        writeln!(writer, "          {} {} 0", body.position[0], body.position[1])?;
+    }
+    writeln!(writer, "        </DataArray>")?;
+    writeln!(writer, "      </Points>")?;
+
+    // One vertex cell per body so ParaView renders the points without a glyph filter
+    writeln!(writer, "      <Verts>")?;
+    writeln!(writer, r#"        <DataArray type="Int64" Name="connectivity" format="ascii">"#)?;
+    for i in 0..n {
+        writeln!(writer, "          {}", i)?;
+    }
+    writeln!(writer, "        </DataArray>")?;
+    writeln!(writer, r#"        <DataArray type="Int64" Name="offsets" format="ascii">"#)?;
+    for i in 0..n {
+        writeln!(writer, "          {}", i + 1)?;
+    }
+    writeln!(writer, "        </DataArray>")?;
+    writeln!(writer, "      </Verts>")?;
+
+    writeln!(writer, "    </Piece>")?;
+    writeln!(writer, "  </PolyData>")?;
+    writeln!(writer, "</VTKFile>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn save_vtp<P: AsRef<Path>>(path: P, bodies: &[Body]) -> Result<(), IoError> {
+    write_vtp(BufWriter::new(File::create(path)?), bodies)
+}
+
+/// Writes a `.pvd` collection; `entries` are `(time, file)` with files relative to the index.
+pub fn write_pvd<W: Write>(mut writer: W, entries: &[(f32, String)]) -> Result<(), IoError> {
+    writeln!(writer, r#"<?xml version="1.0"?>"#)?;
+    writeln!(writer, r#"<VTKFile type="Collection" version="0.1" byte_order="LittleEndian">"#)?;
+    writeln!(writer, "  <Collection>")?;
+    for (time, file) in entries {
+        writeln!(
+            writer,
+            r#"    <DataSet timestep="{}" group="" part="0" file="{}"/>"#,
+            time,
+            escape_attribute(file)
+        )?;
+    }
+    writeln!(writer, "  </Collection>")?;
+    writeln!(writer, "</VTKFile>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes numbered `.vtp` snapshots into a directory and keeps `<prefix>.pvd` up to date,
+/// so a running simulation can already be opened in ParaView.
+pub struct VtkSeries {
+    dir: PathBuf,
+    prefix: String,
+    entries: Vec<(f32, String)>,
+}
+
+impl VtkSeries {
+    pub fn new<P: AsRef<Path>>(dir: P, prefix: &str) -> Result<Self, IoError> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: prefix.to_string(),
+            entries: Vec::new(),
+        })
+    }
+
+    /// Writes the next snapshot and returns its path.
+    pub fn write_snapshot(&mut self, time: f32, bodies: &[Body]) -> Result<PathBuf, IoError> {
+        let file = format!("{}_{:06}.vtp", self.prefix, self.entries.len());
+        let path = self.dir.join(&file);
+        save_vtp(&path, bodies)?;
+        self.entries.push((time, file));
+
+        let index = BufWriter::new(File::create(self.pvd_path())?);
+        write_pvd(index, &self.entries)?;
+        Ok(path)
+    }
+
+    pub fn pvd_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.pvd", self.prefix))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,92 @@
+//! Bodies whose motion is prescribed rather than integrated.
+//!
+//! The motion flag lives in `Body::padding0`, which the WGSL kernel reads as a `u32`. Fixed
+//! and kinematic bodies still pull on everything else but feel no force themselves: a fixed
+//! body never moves, a kinematic one drifts with its own velocity unless a `Trajectory` is
+//! attached, in which case its position and velocity are set from the trajectory at the end
+//! of every step. A trajectory belongs to a body index: `set_bodies` keeps it only while that
+//! index is still in range and its body still kinematic. Unknown flags count as free and are
+//! stepped without being cleared, on the CPU and in the WGSL kernel alike.
+use crate::nbody::periodic;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use std::sync::Arc;
+
+/// Position and velocity as a function of simulation time.
+pub type Trajectory = Arc<dyn Fn(f32) -> ([f32; 2], [f32; 2]) + Send + Sync>;
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Motion {
+    #[default]
+    Free = 0,
+    Fixed = 1,
+    Kinematic = 2,
+}
+
+impl Motion {
+    pub fn from_flag(flag: u32) -> Self {
+        match flag {
+            0 => Motion::Free,
+            1 => Motion::Fixed,
+            2 => Motion::Kinematic,
+            _ => Motion::Free,
+        }
+    }
+}
+
+impl Body {
+    #[inline]
+    pub fn motion(&self) -> Motion {
+        Motion::from_flag(u32::from_ne_bytes(self.padding0))
+    }
+
+    #[inline]
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.padding0 = (motion as u32).to_ne_bytes();
+        self
+    }
+}
+
+/// The kernels' update for fixed and kinematic bodies, `None` for free ones.
+#[inline]
+pub(crate) fn driven_update(body: &Body, params: &SimulationParams) -> Option<Body> {
+    match body.motion() {
+        Motion::Free => None,
+        Motion::Fixed => Some(*body),
+        Motion::Kinematic => {
+            let mut position = [
+                body.position[0] + body.velocity[0] * params.dt,
+                body.position[1] + body.velocity[1] * params.dt,
+            ];
+            if periodic::is_periodic(params) {
+                position = position.map(|x| periodic::wrap(x, params.box_size));
+            }
+            Some(Body { position, ..*body })
+        }
+    }
+}
+
+/// Drops the trajectories whose body is gone or no longer kinematic in `bodies`.
+pub(crate) fn retain_trajectories(trajectories: &mut Vec<(usize, Trajectory)>, bodies: &[Body]) {
+    trajectories.retain(|(index, _)| bodies.get(*index).is_some_and(|body| body.motion() == Motion::Kinematic));
+}
+
+/// Renumbers trajectories for the bodies at the ascending indices `kept`, dropping the rest.
+pub(crate) fn remap_trajectories(trajectories: &mut Vec<(usize, Trajectory)>, kept: &[usize]) {
+    trajectories.retain_mut(|(index, _)| match kept.binary_search(index) {
+        Ok(new_index) => {
+            *index = new_index;
+            true
+        }
+        Err(_) => false,
+    });
+}
+
+/// Puts every body with a trajectory where it is at `time`.
+pub(crate) fn apply_trajectories(bodies: &mut [Body], trajectories: &[(usize, Trajectory)], time: f32) {
+    for (index, trajectory) in trajectories {
+        let (position, velocity) = trajectory(time);
+        bodies[*index].position = position;
+        bodies[*index].velocity = velocity;
+    }
+}
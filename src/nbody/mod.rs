@@ -7,17 +7,42 @@ pub mod cpu_core;      // Shared CPU functions
 pub mod simd_single;
 pub mod simd_rayon;
 pub mod simd_core;     // Shared SIMD functions
+pub mod periodic;      // Minimum image and Ewald summation
+pub mod post_newtonian; // 1PN (EIH) correction for the CPU kernels
+pub mod rotating_frame; // Coriolis and centrifugal terms, frame conversions
+pub mod cosmology;     // Comoving integration on a Friedmann background
+pub mod external_field; // Background potentials added to self-gravity
+pub mod kinematics;    // Fixed and prescribed-motion bodies
+pub mod species;       // Particle types with their own softening and coupling
+pub mod dissipation;   // Drag and dynamical friction, split around the gravity step
 pub mod gpu;
+pub mod io;            // Import/export of body sets
+pub mod initial_conditions; // Equilibrium models behind utils
+pub mod recorder;      // Trajectory sampling around any Simulation
+pub mod escape;        // Escaper detection and removal around any Simulation
+pub mod diagnostics;   // Energy, momentum and virial ratio
+pub mod special;       // erf and erfc shared by Ewald, drag and profiles
 
 #[cfg(test)]
 mod tests;
 pub mod simd_alligned_core;
 pub mod shader_types;
 
+pub use cosmology::{Comoving, Cosmology};
 pub use cpu_rayon::CpuMultiThreaded;
 pub use cpu_single::CpuSingleThreaded;
+pub use dissipation::{
+    ClosureForce, CompositeForce, Dissipative, DissipativeForce, DynamicalFriction, LinearDrag, QuadraticDrag,
+};
+pub use escape::{EscapeMonitor, EscapePolicy, Escaper};
+pub use external_field::{
+    ClosureField, CompositeField, ExternalField, LogarithmicField, MiyamotoNagaiField, PointMassField,
+};
 pub use gpu::GpuSimulator;
+pub use kinematics::{Motion, Trajectory};
+pub use recorder::{RecorderSink, TrajectoryRecorder};
 pub use simd_rayon::SimdMultiThreaded;
+pub use species::SpeciesTable;
 pub use simd_single::SimdSingleThreaded;
 pub use simulation_state::SimulationState;
 pub use simulation_trait::Simulation;
@@ -0,0 +1,109 @@
+//! First post-Newtonian (Einstein–Infeld–Hoffmann) correction to the pairwise gravity.
+//!
+//! Enabled by `speed_of_light > 0` in `SimulationParams`. The EIH acceleration of a body
+//! depends on the Newtonian acceleration and potential of every other body, so it takes two
+//! passes over the massive bodies: `newtonian_field` for all bodies, then `correction`. The
+//! CPU kernels add the correction to their Newtonian sum; the GPU kernel is Newtonian only.
+//! Separations use the same `epsilon` floor as the kernels and need open boundaries, which
+//! `check_params` enforces whenever a backend takes new parameters; species tables do not
+//! apply to the correction.
+use crate::nbody::periodic;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+
+#[inline]
+pub fn is_enabled(params: &SimulationParams) -> bool {
+    params.speed_of_light > 0.0
+}
+
+/// Rejects parameters the correction can't handle; the backends call it from `new` and
+/// `set_params` rather than in the force loop.
+pub fn check_params(params: &SimulationParams) {
+    assert!(!is_enabled(params) || !periodic::is_periodic(params), "1PN corrections need open boundaries");
+}
+
+/// Newtonian acceleration of a body and the potential depth `Σ G m / r` at its position.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NewtonianField {
+    pub acceleration: [f32; 2],
+    pub potential: f32,
+}
+
+#[inline]
+fn separation(from: &Body, to: &Body, params: &SimulationParams) -> ([f32; 2], f32) {
+    let r_vec = [to.position[0] - from.position[0], to.position[1] - from.position[1]];
+    let r = (r_vec[0] * r_vec[0] + r_vec[1] * r_vec[1]).max(params.epsilon).sqrt();
+    (r_vec, r)
+}
+
+/// First pass: the Newtonian field at body `index` from the massive bodies.
+pub fn newtonian_field(index: usize, bodies: &[Body], massive: &[usize], params: &SimulationParams) -> NewtonianField {
+    let current = &bodies[index];
+    let mut field = NewtonianField::default();
+    for &j in massive {
+        if j == index {
+            continue;
+        }
+        let (r_vec, r) = separation(current, &bodies[j], params);
+        let gm = params.g_constant * bodies[j].mass;
+        field.acceleration[0] += gm * r_vec[0] / (r * r * r);
+        field.acceleration[1] += gm * r_vec[1] / (r * r * r);
+        field.potential += gm / r;
+    }
+    field
+}
+
+/// Second pass: the 1PN acceleration of body `index` on top of the Newtonian one, given the
+/// first-pass `fields` of all bodies.
+pub fn correction(
+    index: usize,
+    bodies: &[Body],
+    massive: &[usize],
+    params: &SimulationParams,
+    fields: &[NewtonianField],
+) -> [f32; 2] {
+    let c2 = params.speed_of_light * params.speed_of_light;
+    let a = &bodies[index];
+    let va = a.velocity;
+    let va2 = va[0] * va[0] + va[1] * va[1];
+    let mut correction = [0.0f32; 2];
+
+    for &j in massive {
+        if j == index {
+            continue;
+        }
+        let b = &bodies[j];
+        let vb = b.velocity;
+        let ab = fields[j].acceleration;
+        // r_vec points from a to b, n_ab = -r_vec / r
+        let (r_vec, r) = separation(a, b, params);
+        let n = [-r_vec[0] / r, -r_vec[1] / r];
+        let gm = params.g_constant * b.mass;
+
+        let vb2 = vb[0] * vb[0] + vb[1] * vb[1];
+        let va_vb = va[0] * vb[0] + va[1] * vb[1];
+        let n_vb = n[0] * vb[0] + n[1] * vb[1];
+        let r_ab = r_vec[0] * ab[0] + r_vec[1] * ab[1];
+        let bracket = -4.0 * fields[index].potential - fields[j].potential + va2 + 2.0 * vb2 - 4.0 * va_vb
+            - 1.5 * n_vb * n_vb
+            + 0.5 * r_ab;
+        let newtonian = gm / (r * r);
+        correction[0] += newtonian * bracket * (r_vec[0] / r);
+        correction[1] += newtonian * bracket * (r_vec[1] / r);
+
+        let n_dot = n[0] * (4.0 * va[0] - 3.0 * vb[0]) + n[1] * (4.0 * va[1] - 3.0 * vb[1]);
+        correction[0] += newtonian * n_dot * (va[0] - vb[0]);
+        correction[1] += newtonian * n_dot * (va[1] - vb[1]);
+
+        correction[0] += 3.5 * gm / r * ab[0];
+        correction[1] += 3.5 * gm / r * ab[1];
+    }
+
+    [correction[0] / c2, correction[1] / c2]
+}
+
+/// Both passes over all bodies, one thread.
+pub fn corrections(bodies: &[Body], massive: &[usize], params: &SimulationParams) -> Vec<[f32; 2]> {
+    let fields: Vec<NewtonianField> =
+        (0..bodies.len()).map(|i| newtonian_field(i, bodies, massive, params)).collect();
+    (0..bodies.len()).map(|i| correction(i, bodies, massive, params, &fields)).collect()
+}
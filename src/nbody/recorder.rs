@@ -0,0 +1,193 @@
+//! Trajectory sampling around any `Simulation`, into memory or a CSV stream.
+//!
+//! A failing stream does not stop the run: `step` keeps the first write error and `finish`
+//! returns it.
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::simulation_trait::Simulation;
+use std::collections::VecDeque;
+use std::io::Write;
+
+/// Where recorded samples go.
+pub enum RecorderSink {
+    /// Keep the most recent `capacity` samples in memory.
+    RingBuffer { capacity: usize },
+    /// Append CSV rows `step,time,id,x,y,vx,vy,mass` to a writer, header first.
+    Stream(Box<dyn Write>),
+}
+
+/// Bodies at one sampling point, ids are indices into `get_bodies()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub step: u64,
+    pub time: f32,
+    pub bodies: Vec<(usize, Body)>,
+}
+
+/// Wraps any `Simulation` and samples its bodies every `every` steps.
+///
+/// `step(n)` is split into chunks that end on sampling points, so the wrapped backend only
+/// reads its bodies back there - for the GPU backend that is the only readback.
+pub struct TrajectoryRecorder<S: Simulation> {
+    inner: S,
+    every: usize,
+    ids: Option<Vec<usize>>,
+    species: Option<Vec<u32>>,
+    sink: RecorderSink,
+    samples: VecDeque<Sample>,
+    steps_taken: u64,
+    time: f32,
+    header_written: bool,
+    error: Option<std::io::Error>,
+}
+
+impl<S: Simulation> TrajectoryRecorder<S> {
+    pub fn new(inner: S, every: usize, sink: RecorderSink) -> Self {
+        assert!(every > 0, "sampling interval must be at least one step");
+        if let RecorderSink::RingBuffer { capacity } = sink {
+            assert!(capacity > 0, "ring buffer needs room for at least one sample");
+        }
+        Self {
+            inner,
+            every,
+            ids: None,
+            species: None,
+            sink,
+            samples: VecDeque::new(),
+            steps_taken: 0,
+            time: 0.0,
+            header_written: false,
+            error: None,
+        }
+    }
+
+    /// Only record the given body indices.
+    pub fn with_ids(mut self, ids: Vec<usize>) -> Self {
+        self.ids = Some(ids);
+        self
+    }
+
+    /// Only record bodies of the given species, e.g. stars but not dark matter.
+    pub fn with_species(mut self, species: Vec<u32>) -> Self {
+        self.species = Some(species);
+        self
+    }
+
+    /// Samples in chronological order; always empty when streaming.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    #[inline]
+    pub fn steps_taken(&self) -> u64 {
+        self.steps_taken
+    }
+
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_parts(self) -> (S, RecorderSink) {
+        (self.inner, self.sink)
+    }
+
+    /// Flushes the stream and returns the first write error of any `step` so far.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if let RecorderSink::Stream(writer) = &mut self.sink {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Records the current state right away, e.g. the initial conditions.
+    pub fn record(&mut self) -> std::io::Result<()> {
+        let all = self.inner.get_bodies();
+        let mut bodies: Vec<(usize, Body)> = match &self.ids {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|&id| all.get(id).map(|body| (id, *body)))
+                .collect(),
+            None => all.into_iter().enumerate().collect(),
+        };
+        if let Some(species) = &self.species {
+            bodies.retain(|(_, body)| species.contains(&body.species()));
+        }
+        let sample = Sample { step: self.steps_taken, time: self.time, bodies };
+
+        match &mut self.sink {
+            RecorderSink::RingBuffer { capacity } => {
+                if self.samples.len() == *capacity {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(sample);
+            }
+            RecorderSink::Stream(writer) => {
+                if !self.header_written {
+                    writeln!(writer, "step,time,id,x,y,vx,vy,mass")?;
+                    self.header_written = true;
+                }
+                for (id, body) in &sample.bodies {
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{},{},{},{}",
+                        sample.step, sample.time, id,
+                        body.position[0], body.position[1],
+                        body.velocity[0], body.velocity[1],
+                        body.mass
+                    )?;
+                }
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Simulation> Simulation for TrajectoryRecorder<S> {
+    fn step(&mut self, steps: usize) {
+        let mut remaining = steps;
+        while remaining > 0 {
+            let until_sample = self.every - (self.steps_taken % self.every as u64) as usize;
+            let chunk = until_sample.min(remaining);
+
+            self.inner.step(chunk);
+            self.steps_taken += chunk as u64;
+            self.time += chunk as f32 * self.inner.get_params().dt;
+            remaining -= chunk;
+
+            if self.steps_taken.is_multiple_of(self.every as u64)
+                && let Err(err) = self.record()
+            {
+                self.error.get_or_insert(err);
+            }
+        }
+    }
+
+    fn get_bodies(&self) -> Vec<Body> {
+        self.inner.get_bodies()
+    }
+
+    fn set_bodies(&mut self, bodies: Vec<Body>) {
+        self.inner.set_bodies(bodies);
+    }
+
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        self.inner.retain_bodies(kept);
+    }
+
+    fn get_params(&self) -> &SimulationParams {
+        self.inner.get_params()
+    }
+
+    fn set_params(&mut self, simulation_params: SimulationParams) {
+        self.inner.set_params(simulation_params);
+    }
+}
@@ -0,0 +1,68 @@
+//! Integration in a frame rotating with constant angular velocity `omega` about the z axis.
+//!
+//! With `omega != 0` in `SimulationParams` every body also feels the centrifugal
+//! acceleration `Ω² r` and the Coriolis acceleration `-2 Ω × v`. The velocity update is split
+//! like the Boris scheme: half a kick with the position-dependent forces, the exact Coriolis
+//! rotation of the velocity by `-2Ω dt`, then the other half kick. Positions and velocities
+//! are frame quantities; `to_inertial` and `to_rotating` convert them, with both frames
+//! coinciding at time 0. `shaders/nbody.wgsl` mirrors `kick`.
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+
+#[inline]
+pub fn is_rotating(params: &SimulationParams) -> bool {
+    params.omega != 0.0
+}
+
+#[inline]
+fn rotate(v: [f32; 2], angle: f32) -> [f32; 2] {
+    let (sin, cos) = angle.sin_cos();
+    [cos * v[0] - sin * v[1], sin * v[0] + cos * v[1]]
+}
+
+/// Velocity after one step under `acceleration`, plus the frame forces when rotating.
+#[inline]
+pub fn kick(velocity: [f32; 2], acceleration: [f32; 2], position: [f32; 2], params: &SimulationParams) -> [f32; 2] {
+    if !is_rotating(params) {
+        return [velocity[0] + acceleration[0] * params.dt, velocity[1] + acceleration[1] * params.dt];
+    }
+    let omega2 = params.omega * params.omega;
+    let half = 0.5 * params.dt;
+    let a = [acceleration[0] + omega2 * position[0], acceleration[1] + omega2 * position[1]];
+    let v = [velocity[0] + a[0] * half, velocity[1] + a[1] * half];
+    let v = rotate(v, -2.0 * params.omega * params.dt);
+    [v[0] + a[0] * half, v[1] + a[1] * half]
+}
+
+/// Bodies of a frame rotating at `omega` at `time`, seen from the inertial frame.
+pub fn to_inertial(bodies: &[Body], omega: f32, time: f32) -> Vec<Body> {
+    let angle = omega * time;
+    bodies
+        .iter()
+        .map(|body| {
+            let p = body.position;
+            let v = [body.velocity[0] - omega * p[1], body.velocity[1] + omega * p[0]];
+            Body { position: rotate(p, angle), velocity: rotate(v, angle), ..*body }
+        })
+        .collect()
+}
+
+/// Inertial bodies at `time`, seen from a frame rotating at `omega`.
+pub fn to_rotating(bodies: &[Body], omega: f32, time: f32) -> Vec<Body> {
+    let angle = -omega * time;
+    bodies
+        .iter()
+        .map(|body| {
+            let p = rotate(body.position, angle);
+            let v = rotate(body.velocity, angle);
+            Body { position: p, velocity: [v[0] + omega * p[1], v[1] - omega * p[0]], ..*body }
+        })
+        .collect()
+}
+
+/// Jacobi integral `v²/2 + Φ - Ω² r²/2` of a body in the frame, given the potential `Φ` at
+/// its position; conserved when the potential is static in the frame.
+pub fn jacobi_integral(body: &Body, potential: f32, omega: f32) -> f32 {
+    let v2 = body.velocity[0].powi(2) + body.velocity[1].powi(2);
+    let r2 = body.position[0].powi(2) + body.position[1].powi(2);
+    0.5 * v2 + potential - 0.5 * omega * omega * r2
+}
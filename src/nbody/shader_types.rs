@@ -1,8 +1,13 @@
-#![allow(unused, non_snake_case, non_camel_case_types, non_upper_case_globals)]
-include!(concat!(
-env!("OUT_DIR"),
-"/shaders_types.rs"
-));
+// wgsl_bindgen output: embedded shader constants and generated borrows trip these lints
+#[allow(unused, non_snake_case, non_camel_case_types, non_upper_case_globals)]
+#[allow(clippy::approx_constant, clippy::needless_borrow, clippy::redundant_static_lifetimes)]
+mod generated {
+    include!(concat!(
+    env!("OUT_DIR"),
+    "/shaders_types.rs"
+    ));
+}
+pub use generated::*;
 
 use crate::nbody::shader_types::nbody::SimulationParams;
 
@@ -12,6 +17,10 @@ impl Default for SimulationParams {
             dt: 0.016,        // ~60 FPS
             epsilon: 1e-6,
             g_constant: 1.0,
+            box_size: 0.0,    // open boundaries
+            ewald: 0,
+            speed_of_light: 0.0, // Newtonian
+            omega: 0.0,       // inertial frame
         }
     }
 }
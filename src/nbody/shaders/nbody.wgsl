@@ -4,8 +4,8 @@ struct Body {
     position: vec2<f32>,
     velocity: vec2<f32>,
     mass: f32,
-    padding0: f32,
-    padding1: f32,
+    padding0: u32,  // motion flag: 0 = free, 1 = fixed, 2 = kinematic, see kinematics.rs
+    padding1: u32,  // species index, see species.rs
     padding2: f32,
 }
 
@@ -13,6 +13,39 @@ struct SimulationParams {
     dt: f32,
     epsilon: f32,
     g_constant: f32,
+    box_size: f32,  // 0 = open boundaries
+    ewald: u32,     // != 0 sums over all periodic images
+    speed_of_light: f32,  // > 0 enables 1PN on the CPU; must be 0 here
+    omega: f32,           // angular velocity of the frame, see rotating_frame.rs
+}
+
+// Analytic external field term, see external_field.rs
+struct FieldTerm {
+    kind: u32,        // 0 = unused, 1 = point mass / Miyamoto-Nagai, 2 = logarithmic
+    strength: f32,    // GM, or v0^2 for the logarithmic field
+    scale: f32,       // softening^2 or (a + b)^2, or core radius^2
+    flattening: f32,  // 1 / q^2 of the logarithmic field
+    centre: vec2<f32>,
+    padding0: f32,
+    padding1: f32,
+}
+
+struct ExternalFields {
+    count: u32,
+    padding0: f32,
+    padding1: f32,
+    padding2: f32,
+    terms: array<FieldTerm, 4>,
+}
+
+// Per-species softening and coupling[target][source], see species.rs
+struct SpeciesParams {
+    enabled: u32,
+    padding0: f32,
+    padding1: f32,
+    padding2: f32,
+    softening: vec4<f32>,
+    coupling: array<vec4<f32>, 4>,
 }
 
 @group(0) @binding(0)
@@ -27,6 +60,102 @@ var<uniform> params: SimulationParams;
 @group(0) @binding(3)
 var<uniform> n_bodies: u32;
 
+@group(0) @binding(4)
+var<uniform> external_fields: ExternalFields;
+
+// Indices of the bodies with mass; massless test particles exert no gravity
+@group(0) @binding(5)
+var<storage, read> massive_indices: array<u32>;
+
+@group(0) @binding(6)
+var<uniform> n_massive: u32;
+
+@group(0) @binding(7)
+var<uniform> species: SpeciesParams;
+
+// Periodic box [-L/2, L/2)^2, see periodic.rs
+const PI: f32 = 3.14159265;
+const REAL_IMAGES: i32 = 2;
+const FOURIER_MODES: i32 = 3;
+
+fn wrap(x: vec2<f32>) -> vec2<f32> {
+    return x - params.box_size * floor((x + 0.5 * params.box_size) / params.box_size);
+}
+
+fn minimum_image(d: vec2<f32>) -> vec2<f32> {
+    return d - params.box_size * round(d / params.box_size);
+}
+
+// Abramowitz & Stegun 7.1.26, x >= 0
+fn erfc_approx(x: f32) -> f32 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    return poly * exp(-x * x);
+}
+
+fn ewald_acceleration(r_vec: vec2<f32>, epsilon: f32) -> vec2<f32> {
+    let box_size = params.box_size;
+    let d = minimum_image(r_vec);
+    let alpha = 2.0 / box_size;
+    var acceleration = vec2<f32>(0.0, 0.0);
+
+    for (var nx = -REAL_IMAGES; nx <= REAL_IMAGES; nx = nx + 1) {
+        for (var ny = -REAL_IMAGES; ny <= REAL_IMAGES; ny = ny + 1) {
+            let u = d + vec2<f32>(f32(nx), f32(ny)) * box_size;
+            let u_squared = max(dot(u, u), epsilon);
+            let u_distance = sqrt(u_squared);
+            let magnitude = erfc_approx(alpha * u_distance) / u_squared
+                + 2.0 * alpha / sqrt(PI) * exp(-alpha * alpha * u_squared) / u_distance;
+            acceleration = acceleration + magnitude * (u / u_distance);
+        }
+    }
+
+    let k_unit = 2.0 * PI / box_size;
+    for (var mx = -FOURIER_MODES; mx <= FOURIER_MODES; mx = mx + 1) {
+        for (var my = -FOURIER_MODES; my <= FOURIER_MODES; my = my + 1) {
+            if (mx == 0 && my == 0) {
+                continue;
+            }
+            let k = vec2<f32>(f32(mx), f32(my)) * k_unit;
+            let k_length = length(k);
+            let coefficient = 2.0 * PI / (k_length * box_size * box_size)
+                * erfc_approx(k_length / (2.0 * alpha))
+                * sin(dot(k, d));
+            acceleration = acceleration + coefficient * k;
+        }
+    }
+
+    return acceleration;
+}
+
+fn external_acceleration(position: vec2<f32>) -> vec2<f32> {
+    var acceleration = vec2<f32>(0.0, 0.0);
+    for (var t = 0u; t < external_fields.count; t = t + 1u) {
+        let term = external_fields.terms[t];
+        let d = position - term.centre;
+        if (term.kind == 1u) {
+            let r_squared = dot(d, d) + term.scale;
+            acceleration = acceleration - term.strength * d / (r_squared * sqrt(r_squared));
+        } else if (term.kind == 2u) {
+            let m_squared = term.scale + d.x * d.x + d.y * d.y * term.flattening;
+            acceleration = acceleration - term.strength * vec2<f32>(d.x, d.y * term.flattening) / m_squared;
+        }
+    }
+    return acceleration;
+}
+
+// Boris-style kick: half kick, exact Coriolis rotation by -2 omega dt, half kick
+fn kick(velocity: vec2<f32>, acceleration: vec2<f32>, position: vec2<f32>) -> vec2<f32> {
+    if (params.omega == 0.0) {
+        return velocity + acceleration * params.dt;
+    }
+    let a = acceleration + params.omega * params.omega * position;
+    let v = velocity + a * (0.5 * params.dt);
+    let angle = -2.0 * params.omega * params.dt;
+    let rotated = vec2<f32>(cos(angle) * v.x - sin(angle) * v.y, sin(angle) * v.x + cos(angle) * v.y);
+    return rotated + a * (0.5 * params.dt);
+}
+
 @compute @workgroup_size(256)
 fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let i = global_id.x;
@@ -36,31 +165,68 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     }
 
     let current = bodies_in[i];
-    var force = vec2<f32>(0.0, 0.0);
 
-    for (var j = 0u; j < n_bodies; j = j + 1u) {
+    // Fixed and kinematic bodies feel no force; trajectories are applied on the CPU.
+    // Unknown flags integrate like free bodies and are kept, as in the CPU kernels.
+    if (current.padding0 == 1u || current.padding0 == 2u) {
+        var position = current.position;
+        if (current.padding0 == 2u) {
+            position = position + current.velocity * params.dt;
+            if (params.box_size > 0.0) {
+                position = wrap(position);
+            }
+        }
+        bodies_out[i] = current;
+        bodies_out[i].position = position;
+        return;
+    }
+    var acceleration = vec2<f32>(0.0, 0.0);
+
+    for (var k = 0u; k < n_massive; k = k + 1u) {
+        let j = massive_indices[k];
         if (i == j) {
             continue;
         }
 
         let other = bodies_in[j];
-        let r_vec = other.position - current.position;
-        let r_squared = max(dot(r_vec, r_vec), params.epsilon);
+        var coupling = 1.0;
+        var epsilon = params.epsilon;
+        if (species.enabled != 0u) {
+            coupling = species.coupling[current.padding1][other.padding1];
+            epsilon = max(species.softening[current.padding1], species.softening[other.padding1]);
+            if (coupling == 0.0) {
+                continue;
+            }
+        }
+        let strength = coupling * params.g_constant * other.mass;
+
+        var r_vec = other.position - current.position;
+        if (params.box_size > 0.0) {
+            if (params.ewald != 0u) {
+                acceleration = acceleration + strength * ewald_acceleration(r_vec, epsilon);
+                continue;
+            }
+            r_vec = minimum_image(r_vec);
+        }
+        let r_squared = max(dot(r_vec, r_vec), epsilon);
         let r_distance = sqrt(r_squared);
-        let force_magnitude = params.g_constant * current.mass * other.mass / r_squared;
-        force = force + force_magnitude * (r_vec / r_distance);
+        let acceleration_magnitude = strength / r_squared;
+        acceleration = acceleration + acceleration_magnitude * (r_vec / r_distance);
     }
 
-    let acceleration = force / current.mass;
+    acceleration = acceleration + external_acceleration(current.position);
 
-    let new_velocity = current.velocity + acceleration * params.dt;
+    let new_velocity = kick(current.velocity, acceleration, current.position);
 
-    let new_position = current.position + new_velocity * params.dt;
+    var new_position = current.position + new_velocity * params.dt;
+    if (params.box_size > 0.0) {
+        new_position = wrap(new_position);
+    }
 
     bodies_out[i].position = new_position;
     bodies_out[i].velocity = new_velocity;
     bodies_out[i].mass = current.mass;
-    bodies_out[i].padding0 = 0.0;
-    bodies_out[i].padding1 = 0.0;
+    bodies_out[i].padding0 = current.padding0;
+    bodies_out[i].padding1 = current.padding1;
     bodies_out[i].padding2 = 0.0;
 }
@@ -1,7 +1,15 @@
 use std::simd::{f32x8, StdFloat};
 use std::simd::prelude::SimdFloat;
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics::{self, Motion, Trajectory};
+use crate::nbody::periodic;
+use crate::nbody::post_newtonian;
+use crate::nbody::rotating_frame;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::simulation_state::massive_indices;
+use crate::nbody::species::{self, SpeciesTable, MAX_SPECIES};
 use crate::nbody::Simulation;
+use std::sync::Arc;
 
 #[derive(Default)]
 pub struct SimdAlignedNBodyCore {
@@ -11,6 +19,13 @@ pub struct SimdAlignedNBodyCore {
     velocity_y: Vec<f32>,
     mass: Vec<f32>,
     params: SimulationParams,
+    external_field: Option<Arc<dyn ExternalField>>,
+    massive: Vec<usize>,
+    motion: Vec<Motion>,
+    species: Vec<u32>,
+    species_table: Option<SpeciesTable>,
+    trajectories: Vec<(usize, Trajectory)>,
+    time: f32,
 }
 
 impl SimdAlignedNBodyCore {
@@ -22,10 +37,106 @@ impl SimdAlignedNBodyCore {
             velocity_y: Vec::with_capacity(0),
             mass: Vec::with_capacity(0),
             params: Default::default(),
+            external_field: None,
+            massive: Vec::new(),
+            motion: Vec::new(),
+            species: Vec::new(),
+            species_table: None,
+            trajectories: Vec::new(),
+            time: 0.0,
         };
         ret.set_bodies(bodies);
         ret
     }
+
+    pub fn set_external_field(&mut self, field: Option<Arc<dyn ExternalField>>) {
+        self.external_field = field;
+    }
+
+    /// Makes body `index` kinematic and moves it onto `trajectory` at the current time; see
+    /// `SimulationState::set_trajectory` for how `set_bodies` treats it.
+    pub fn set_trajectory(&mut self, index: usize, trajectory: Trajectory) {
+        self.trajectories.retain(|(i, _)| *i != index);
+        self.trajectories.push((index, trajectory));
+        self.motion[index] = Motion::Kinematic;
+        self.apply_trajectories();
+    }
+
+    pub fn set_species_table(&mut self, table: Option<SpeciesTable>) {
+        self.species_table = table;
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    fn apply_trajectories(&mut self) {
+        for (index, trajectory) in &self.trajectories {
+            let ([x, y], [vx, vy]) = trajectory(self.time);
+            self.position_x[*index] = x;
+            self.position_y[*index] = y;
+            self.velocity_x[*index] = vx;
+            self.velocity_y[*index] = vy;
+        }
+    }
+
+    #[inline]
+    pub fn position_x(&self) -> &[f32] {
+        &self.position_x
+    }
+
+    #[inline]
+    pub fn position_y(&self) -> &[f32] {
+        &self.position_y
+    }
+
+    #[inline]
+    pub fn velocity_x(&self) -> &[f32] {
+        &self.velocity_x
+    }
+
+    #[inline]
+    pub fn velocity_y(&self) -> &[f32] {
+        &self.velocity_y
+    }
+
+    #[inline]
+    pub fn mass(&self) -> &[f32] {
+        &self.mass
+    }
+
+    #[inline]
+    fn scalar_pair_acceleration(&self, i: usize, j: usize) -> [f32; 2] {
+        let mut dx = self.position_x[j] - self.position_x[i];
+        let mut dy = self.position_y[j] - self.position_y[i];
+        let (coupling, epsilon) = match &self.species_table {
+            Some(table) => {
+                let (t, s) = (self.species[i] as usize, self.species[j] as usize);
+                (table.coupling[t][s], table.softening[t].max(table.softening[s]))
+            }
+            None => (1.0, self.params.epsilon),
+        };
+        if coupling == 0.0 {
+            return [0.0, 0.0];
+        }
+        let strength = coupling * self.params.g_constant * self.mass[j];
+
+        if periodic::uses_ewald(&self.params) {
+            let a = periodic::ewald_acceleration([dx, dy], self.params.box_size, epsilon);
+            return [strength * a[0], strength * a[1]];
+        }
+        if periodic::is_periodic(&self.params) {
+            dx = periodic::minimum_image(dx, self.params.box_size);
+            dy = periodic::minimum_image(dy, self.params.box_size);
+        }
+
+        let r2 = (dx*dx + dy*dy).max(epsilon);
+        let r = r2.sqrt();
+        let a = strength / r2;
+        [a * dx / r, a * dy / r]
+    }
+
     #[inline]
     fn simd_step_once(&mut self) {
         let n = self.mass.len();
@@ -37,84 +148,145 @@ impl SimdAlignedNBodyCore {
         let mut new_vel_y = vec![0.0f32; n];
 
         let g = f32x8::splat(self.params.g_constant);
-        let eps = f32x8::splat(self.params.epsilon);
         let dt = self.params.dt;
 
-        let chunks = n / 8;
+        let is_periodic = periodic::is_periodic(&self.params);
+        let box_size = f32x8::splat(self.params.box_size);
+
+        // only bodies with mass pull, packed contiguously for the SIMD loads
+        let m = self.massive.len();
+        let source_x: Vec<f32> = self.massive.iter().map(|&j| self.position_x[j]).collect();
+        let source_y: Vec<f32> = self.massive.iter().map(|&j| self.position_y[j]).collect();
+        // per target species: source masses scaled by the coupling and the pair softening
+        let sources_by_species: Vec<(Vec<f32>, Vec<f32>)> = match &self.species_table {
+            Some(table) => (0..MAX_SPECIES)
+                .map(|t| {
+                    self.massive
+                        .iter()
+                        .map(|&j| {
+                            let s = self.species[j] as usize;
+                            let coupling = table.coupling[t][s];
+                            let eps = if coupling == 0.0 { 1.0 } else { table.softening[t].max(table.softening[s]) };
+                            (coupling * self.mass[j], eps)
+                        })
+                        .unzip()
+                })
+                .collect(),
+            None => vec![(
+                self.massive.iter().map(|&j| self.mass[j]).collect(),
+                vec![self.params.epsilon; m],
+            )],
+        };
+
+        let post_newtonian = post_newtonian::is_enabled(&self.params)
+            .then(|| post_newtonian::corrections(&self.get_bodies(), &self.massive, &self.params));
+
+        // Ewald sums stay scalar
+        let chunks = if periodic::uses_ewald(&self.params) { 0 } else { m / 8 };
 
         for i in 0..n {
+            // fixed and kinematic bodies feel no force
+            if self.motion[i] != Motion::Free {
+                let mut npx = self.position_x[i];
+                let mut npy = self.position_y[i];
+                if self.motion[i] == Motion::Kinematic {
+                    npx += self.velocity_x[i] * dt;
+                    npy += self.velocity_y[i] * dt;
+                    if is_periodic {
+                        npx = periodic::wrap(npx, self.params.box_size);
+                        npy = periodic::wrap(npy, self.params.box_size);
+                    }
+                }
+                new_vel_x[i] = self.velocity_x[i];
+                new_vel_y[i] = self.velocity_y[i];
+                new_pos_x[i] = npx;
+                new_pos_y[i] = npy;
+                continue;
+            }
+
+            let target = if self.species_table.is_some() { self.species[i] as usize } else { 0 };
+            let (source_mass, source_eps) = &sources_by_species[target];
+
             let px = f32x8::splat(self.position_x[i]);
             let py = f32x8::splat(self.position_y[i]);
-            let m_i = f32x8::splat(self.mass[i]);
 
-            let mut fx = f32x8::splat(0.0);
-            let mut fy = f32x8::splat(0.0);
+            let mut ax = f32x8::splat(0.0);
+            let mut ay = f32x8::splat(0.0);
 
             // SIMD loops
             for chunk in 0..chunks {
                 let base = chunk * 8;
+                let sources = &self.massive[base..base + 8];
 
-                if base <= i && i < base + 8 {
+                if sources.contains(&i) {
                     // self interaction in scalar
-                    for j in base..base + 8 {
+                    for &j in sources {
                         if j == i { continue; }
 
-                        let dx = self.position_x[j] - self.position_x[i];
-                        let dy = self.position_y[j] - self.position_y[i];
-
-                        let r2 = (dx*dx + dy*dy).max(self.params.epsilon);
-                        let r = r2.sqrt();
-                        let f = self.params.g_constant * self.mass[i] * self.mass[j] / r2;
-                        fx[0] += f * dx / r;
-                        fy[0] += f * dy / r;
+                        let [a_x, a_y] = self.scalar_pair_acceleration(i, j);
+                        ax[0] += a_x;
+                        ay[0] += a_y;
                     }
                     continue;
                 }
 
                 // SIMD load
-                let ox = f32x8::from_slice(&self.position_x[base..base+8]);
-                let oy = f32x8::from_slice(&self.position_y[base..base+8]);
-                let om = f32x8::from_slice(&self.mass[base..base+8]);
-
-                let dx = ox - px;
-                let dy = oy - py;
+                let ox = f32x8::from_slice(&source_x[base..base+8]);
+                let oy = f32x8::from_slice(&source_y[base..base+8]);
+                let om = f32x8::from_slice(&source_mass[base..base+8]);
+                let eps = f32x8::from_slice(&source_eps[base..base+8]);
+
+                let mut dx = ox - px;
+                let mut dy = oy - py;
+                if is_periodic {
+                    dx -= box_size * (dx / box_size).round();
+                    dy -= box_size * (dy / box_size).round();
+                }
 
                 let r2 = (dx*dx + dy*dy).simd_max(eps);
                 let r = r2.sqrt();
 
-                let f = g * m_i * om / r2;
+                let a = g * om / r2;
 
-                fx += f * dx / r;
-                fy += f * dy / r;
+                ax += a * dx / r;
+                ay += a * dy / r;
             }
 
             // scalar tail
-            for j in chunks * 8 .. n {
+            for &j in &self.massive[chunks * 8..] {
                 if j == i { continue; }
 
-                let dx = self.position_x[j] - self.position_x[i];
-                let dy = self.position_y[j] - self.position_y[i];
-
-                let r2 = (dx*dx + dy*dy).max(self.params.epsilon);
-                let r = r2.sqrt();
-                let f = self.params.g_constant * self.mass[i] * self.mass[j] / r2;
-
-                fx[0] += f * dx / r;
-                fy[0] += f * dy / r;
+                let [a_x, a_y] = self.scalar_pair_acceleration(i, j);
+                ax[0] += a_x;
+                ay[0] += a_y;
             }
 
             // reduce SIMD vector to scalars
-            let fx_sum = fx.reduce_sum();
-            let fy_sum = fy.reduce_sum();
-
-            let ax = fx_sum / self.mass[i];
-            let ay = fy_sum / self.mass[i];
+            let mut ax = ax.reduce_sum();
+            let mut ay = ay.reduce_sum();
+            if let Some(field) = &self.external_field {
+                let [ex, ey] = field.acceleration([self.position_x[i], self.position_y[i]]);
+                ax += ex;
+                ay += ey;
+            }
+            if let Some(corrections) = &post_newtonian {
+                ax += corrections[i][0];
+                ay += corrections[i][1];
+            }
 
-            let nvx = self.velocity_x[i] + ax * dt;
-            let nvy = self.velocity_y[i] + ay * dt;
+            let [nvx, nvy] = rotating_frame::kick(
+                [self.velocity_x[i], self.velocity_y[i]],
+                [ax, ay],
+                [self.position_x[i], self.position_y[i]],
+                &self.params,
+            );
 
-            let npx = self.position_x[i] + nvx * dt;
-            let npy = self.position_y[i] + nvy * dt;
+            let mut npx = self.position_x[i] + nvx * dt;
+            let mut npy = self.position_y[i] + nvy * dt;
+            if is_periodic {
+                npx = periodic::wrap(npx, self.params.box_size);
+                npy = periodic::wrap(npy, self.params.box_size);
+            }
 
             new_vel_x[i] = nvx;
             new_vel_y[i] = nvy;
@@ -127,6 +299,9 @@ impl SimdAlignedNBodyCore {
         self.position_y = new_pos_y;
         self.velocity_x = new_vel_x;
         self.velocity_y = new_vel_y;
+
+        self.time += dt;
+        self.apply_trajectories();
     }
 }
 
@@ -145,13 +320,14 @@ impl Simulation for SimdAlignedNBodyCore{
             bodies.push(Body::new(
                 [self.position_x[i], self.position_y[i]],
                 [self.velocity_x[i], self.velocity_y[i]],
-                self.mass[i])
+                self.mass[i]).with_motion(self.motion[i]).with_species(self.species[i])
             );
         }
 
         bodies
     }
     fn set_bodies(&mut self, bodies: Vec<Body>) {
+        species::check_species(&bodies);
         let len = bodies.len();
 
         self.position_x = vec![0.0; len];
@@ -167,12 +343,23 @@ impl Simulation for SimdAlignedNBodyCore{
             self.velocity_y[i] = body.velocity[1];
             self.mass[i] = body.mass;
         }
+        kinematics::retain_trajectories(&mut self.trajectories, &bodies);
+        self.massive = massive_indices(&bodies);
+        self.motion = bodies.iter().map(|body| body.motion()).collect();
+        self.species = bodies.iter().map(|body| body.species()).collect();
+    }
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        let bodies = self.get_bodies();
+        kinematics::remap_trajectories(&mut self.trajectories, kept);
+        self.set_bodies(kept.iter().map(|&i| bodies[i]).collect());
     }
+
     fn get_params(&self) -> &SimulationParams {
         &self.params
     }
 
     fn set_params(&mut self, simulation_params: SimulationParams) {
+        post_newtonian::check_params(&simulation_params);
         self.params = simulation_params;
     }
 }
\ No newline at end of file
@@ -1,35 +1,47 @@
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics;
+use crate::nbody::periodic;
+use crate::nbody::rotating_frame;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::species::SpeciesTable;
 use std::simd::{f32x8, num::SimdFloat, StdFloat};
 
 #[inline]
 pub fn compute_body_update(
     index: usize,
     all_bodies: &[Body],
+    massive: &[usize],
     params: &SimulationParams,
+    external_field: Option<&dyn ExternalField>,
+    post_newtonian: Option<&[[f32; 2]]>,
+    species: Option<&SpeciesTable>,
 ) -> Body {
-    let n = all_bodies.len();
     let current = &all_bodies[index];
-    let mut force = [0.0f32; 2];
+    if let Some(driven) = kinematics::driven_update(current, params) {
+        return driven;
+    }
+    let mut acceleration = [0.0f32; 2];
 
     let current_pos_x = f32x8::splat(current.position[0]);
     let current_pos_y = f32x8::splat(current.position[1]);
-    let current_mass = f32x8::splat(current.mass);
-    let epsilon = f32x8::splat(params.epsilon);
     let g_constant = f32x8::splat(params.g_constant);
-
-    let mut force_x = f32x8::splat(0.0);
-    let mut force_y = f32x8::splat(0.0);
-
-    let chunks = n / 8;
-    for chunk in 0..chunks {
-        let base_idx = chunk * 8;
-
-        if base_idx <= index && index < base_idx + 8 {
-            for j in base_idx..base_idx + 8 {
+    let is_periodic = periodic::is_periodic(params);
+    let box_size = f32x8::splat(params.box_size);
+
+    let mut acceleration_x = f32x8::splat(0.0);
+    let mut acceleration_y = f32x8::splat(0.0);
+
+    // Only bodies with mass pull; test particles feel the others but exert nothing
+    let chunks = massive.chunks_exact(8);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        // Ewald sums stay scalar
+        if periodic::uses_ewald(params) || chunk.contains(&index) {
+            for &j in chunk {
                 if index == j {
                     continue;
                 }
-                compute_force_scalar(current, &all_bodies[j], params, &mut force);
+                compute_acceleration_scalar(current, &all_bodies[j], params, species, &mut acceleration);
             }
             continue;
         }
@@ -37,74 +49,106 @@ pub fn compute_body_update(
         let mut other_pos_x = [0.0f32; 8];
         let mut other_pos_y = [0.0f32; 8];
         let mut other_mass = [0.0f32; 8];
-
-        for k in 0..8 {
-            let idx = base_idx + k;
-            other_pos_x[k] = all_bodies[idx].position[0];
-            other_pos_y[k] = all_bodies[idx].position[1];
-            other_mass[k] = all_bodies[idx].mass;
+        let mut other_epsilon = [params.epsilon; 8];
+
+        for (k, &j) in chunk.iter().enumerate() {
+            other_pos_x[k] = all_bodies[j].position[0];
+            other_pos_y[k] = all_bodies[j].position[1];
+            other_mass[k] = all_bodies[j].mass;
+            if let Some(table) = species {
+                // An ignored source gets no mass and a harmless floor
+                let (coupling, epsilon) = table.pair(current, &all_bodies[j]);
+                other_mass[k] *= coupling;
+                other_epsilon[k] = if coupling == 0.0 { 1.0 } else { epsilon };
+            }
         }
 
         let other_pos_x_simd = f32x8::from_array(other_pos_x);
         let other_pos_y_simd = f32x8::from_array(other_pos_y);
         let other_mass_simd = f32x8::from_array(other_mass);
+        let epsilon = f32x8::from_array(other_epsilon);
 
-        let r_vec_x = other_pos_x_simd - current_pos_x;
-        let r_vec_y = other_pos_y_simd - current_pos_y;
+        let mut r_vec_x = other_pos_x_simd - current_pos_x;
+        let mut r_vec_y = other_pos_y_simd - current_pos_y;
+        if is_periodic {
+            r_vec_x -= box_size * (r_vec_x / box_size).round();
+            r_vec_y -= box_size * (r_vec_y / box_size).round();
+        }
 
         let r_squared = (r_vec_x * r_vec_x + r_vec_y * r_vec_y).simd_max(epsilon);
         let r_distance = r_squared.sqrt();
 
-        let force_magnitude = g_constant * current_mass * other_mass_simd / r_squared;
+        let acceleration_magnitude = g_constant * other_mass_simd / r_squared;
 
-        let force_dir_x = r_vec_x / r_distance;
-        let force_dir_y = r_vec_y / r_distance;
-
-        force_x += force_magnitude * force_dir_x;
-        force_y += force_magnitude * force_dir_y;
+        acceleration_x += acceleration_magnitude * (r_vec_x / r_distance);
+        acceleration_y += acceleration_magnitude * (r_vec_y / r_distance);
     }
 
-    force[0] += force_x.reduce_sum();
-    force[1] += force_y.reduce_sum();
+    acceleration[0] += acceleration_x.reduce_sum();
+    acceleration[1] += acceleration_y.reduce_sum();
 
-    for j in (chunks * 8)..n {
+    for &j in remainder {
         if index == j {
             continue;
         }
-        compute_force_scalar(current, &all_bodies[j], params, &mut force);
+        compute_acceleration_scalar(current, &all_bodies[j], params, species, &mut acceleration);
     }
 
-    let acceleration = [force[0] / current.mass, force[1] / current.mass];
-    let new_velocity = [
-        current.velocity[0] + acceleration[0] * params.dt,
-        current.velocity[1] + acceleration[1] * params.dt,
-    ];
-    let new_position = [
+    if let Some(field) = external_field {
+        let external = field.acceleration(current.position);
+        acceleration[0] += external[0];
+        acceleration[1] += external[1];
+    }
+    if let Some(corrections) = post_newtonian {
+        acceleration[0] += corrections[index][0];
+        acceleration[1] += corrections[index][1];
+    }
+    let new_velocity = rotating_frame::kick(current.velocity, acceleration, current.position, params);
+    let mut new_position = [
         current.position[0] + new_velocity[0] * params.dt,
         current.position[1] + new_velocity[1] * params.dt,
     ];
+    if is_periodic {
+        new_position = new_position.map(|x| periodic::wrap(x, params.box_size));
+    }
 
-    Body::new(new_position, new_velocity, current.mass)
+    Body { position: new_position, velocity: new_velocity, ..*current }
 }
 
 #[inline]
-fn compute_force_scalar(
+fn compute_acceleration_scalar(
     current: &Body,
     other: &Body,
     params: &SimulationParams,
-    force: &mut [f32; 2],
+    species: Option<&SpeciesTable>,
+    acceleration: &mut [f32; 2],
 ) {
-    let r_vec = [
+    let (coupling, epsilon) = species.map_or((1.0, params.epsilon), |table| table.pair(current, other));
+    if coupling == 0.0 {
+        return;
+    }
+    let strength = coupling * params.g_constant * other.mass;
+
+    let mut r_vec = [
         other.position[0] - current.position[0],
         other.position[1] - current.position[1],
     ];
 
-    let r_squared = (r_vec[0].powi(2) + r_vec[1].powi(2)).max(params.epsilon);
+    if periodic::uses_ewald(params) {
+        let a = periodic::ewald_acceleration(r_vec, params.box_size, epsilon);
+        acceleration[0] += strength * a[0];
+        acceleration[1] += strength * a[1];
+        return;
+    }
+    if periodic::is_periodic(params) {
+        r_vec = r_vec.map(|d| periodic::minimum_image(d, params.box_size));
+    }
+
+    let r_squared = (r_vec[0].powi(2) + r_vec[1].powi(2)).max(epsilon);
     let r_distance = r_squared.sqrt();
 
-    let force_magnitude = params.g_constant * current.mass * other.mass / r_squared;
-    let force_direction = [r_vec[0] / r_distance, r_vec[1] / r_distance];
+    let acceleration_magnitude = strength / r_squared;
 
-    force[0] += force_magnitude * force_direction[0];
-    force[1] += force_magnitude * force_direction[1];
+    acceleration[0] += acceleration_magnitude * r_vec[0] / r_distance;
+    acceleration[1] += acceleration_magnitude * r_vec[1] / r_distance;
 }
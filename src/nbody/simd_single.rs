@@ -1,7 +1,12 @@
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics::Trajectory;
+use crate::nbody::species::SpeciesTable;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
 use crate::nbody::simulation_state::SimulationState;
 use crate::nbody::simulation_trait::Simulation;
 use crate::nbody::simd_core;
+use crate::nbody::post_newtonian;
+use std::sync::Arc;
 
 pub struct SimdSingleThreaded {
     state: SimulationState,
@@ -14,15 +19,37 @@ impl SimdSingleThreaded {
         }
     }
 
+    pub fn set_external_field(&mut self, field: Option<Arc<dyn ExternalField>>) {
+        self.state.set_external_field(field);
+    }
+
+    pub fn set_trajectory(&mut self, index: usize, trajectory: Trajectory) {
+        self.state.set_trajectory(index, trajectory);
+    }
+
+    pub fn set_species_table(&mut self, table: Option<SpeciesTable>) {
+        self.state.set_species_table(table);
+    }
+
+    pub fn time(&self) -> f32 {
+        self.state.time()
+    }
+
     #[inline]
     fn step_once(&mut self) {
         let n = self.state.len();
         let bodies_ref = self.state.bodies();
+        let massive = self.state.massive();
+        let external_field = self.state.external_field();
+        let species = self.state.species();
         let params = self.state.params;
 
+        let post_newtonian = post_newtonian::is_enabled(&params)
+            .then(|| post_newtonian::corrections(bodies_ref, massive, &params));
+
         let new_bodies: Vec<Body> = (0..n)
             .map(|i| {
-                simd_core::compute_body_update(i, bodies_ref, &params)
+                simd_core::compute_body_update(i, bodies_ref, massive, &params, external_field, post_newtonian.as_deref(), species)
             })
             .collect();
 
@@ -45,11 +72,15 @@ impl Simulation for SimdSingleThreaded {
         self.state.set_bodies(bodies);
     }
 
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        self.state.retain_bodies(kept);
+    }
+
     fn get_params(&self) -> &SimulationParams {
         self.state.get_params()
     }
 
     fn set_params(&mut self, simulation_params: SimulationParams) {
-        self.state.params = simulation_params;
+        self.state.set_params(simulation_params);
     }
 }
@@ -1,13 +1,31 @@
+use crate::nbody::external_field::ExternalField;
+use crate::nbody::kinematics::{self, Motion, Trajectory};
+use crate::nbody::post_newtonian;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::species::{self, SpeciesTable};
+use std::sync::Arc;
 
 pub struct SimulationState {
     bodies: Vec<Body>,
     pub(crate) params: SimulationParams,
+    external_field: Option<Arc<dyn ExternalField>>,
+    /// Indices of the bodies with mass; massless ones are test particles and exert no gravity.
+    massive: Vec<usize>,
+    trajectories: Vec<(usize, Trajectory)>,
+    time: f32,
+    species: Option<SpeciesTable>,
+}
+
+pub(crate) fn massive_indices(bodies: &[Body]) -> Vec<usize> {
+    bodies.iter().enumerate().filter(|(_, b)| b.mass != 0.0).map(|(i, _)| i).collect()
 }
 
 impl SimulationState {
     pub fn new(bodies: Vec<Body>, params: SimulationParams) -> Self {
-        Self { bodies, params }
+        post_newtonian::check_params(&params);
+        species::check_species(&bodies);
+        let massive = massive_indices(&bodies);
+        Self { bodies, params, external_field: None, massive, trajectories: Vec::new(), time: 0.0, species: None }
     }
 
     #[inline]
@@ -35,14 +53,91 @@ impl SimulationState {
         &self.params
     }
 
+    #[inline]
+    pub fn set_params(&mut self, params: SimulationParams) {
+        post_newtonian::check_params(&params);
+        self.params = params;
+    }
+
+    #[inline]
+    pub fn massive(&self) -> &[usize] {
+        &self.massive
+    }
+
+    /// Keeps the trajectories whose body index is in range and still kinematic; panics on
+    /// out-of-range species.
     #[inline]
     pub fn set_bodies(&mut self, bodies: Vec<Body>) {
+        species::check_species(&bodies);
+        kinematics::retain_trajectories(&mut self.trajectories, &bodies);
+        self.massive = massive_indices(&bodies);
         self.bodies = bodies;
     }
 
+    /// Keeps the bodies at the ascending indices `kept` and renumbers their trajectories.
+    pub fn retain_bodies(&mut self, kept: &[usize]) {
+        let bodies = kept.iter().map(|&i| self.bodies[i]).collect();
+        self.remap_trajectories(kept);
+        self.set_bodies(bodies);
+    }
+
+    /// The trajectory part of `retain_bodies`, for backends whose bodies live elsewhere.
+    #[inline]
+    pub(crate) fn remap_trajectories(&mut self, kept: &[usize]) {
+        kinematics::remap_trajectories(&mut self.trajectories, kept);
+    }
+
+    #[inline]
+    pub fn external_field(&self) -> Option<&dyn ExternalField> {
+        self.external_field.as_deref()
+    }
+
+    #[inline]
+    pub fn set_external_field(&mut self, field: Option<Arc<dyn ExternalField>>) {
+        self.external_field = field;
+    }
+
+    #[inline]
+    pub fn species(&self) -> Option<&SpeciesTable> {
+        self.species.as_ref()
+    }
+
+    #[inline]
+    pub fn set_species_table(&mut self, table: Option<SpeciesTable>) {
+        self.species = table;
+    }
+
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    #[inline]
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    #[inline]
+    pub fn trajectories(&self) -> &[(usize, Trajectory)] {
+        &self.trajectories
+    }
+
+    /// Makes body `index` kinematic and moves it onto `trajectory` at the current time. The
+    /// trajectory follows the index: `set_bodies` drops it once the index is out of range or
+    /// that body is no longer kinematic.
+    pub fn set_trajectory(&mut self, index: usize, trajectory: Trajectory) {
+        self.trajectories.retain(|(i, _)| *i != index);
+        self.trajectories.push((index, trajectory));
+        self.bodies[index] = self.bodies[index].with_motion(Motion::Kinematic);
+        kinematics::apply_trajectories(&mut self.bodies, &self.trajectories, self.time);
+    }
+
+    /// Replaces the bodies after a step and advances the time; masses must be unchanged.
     #[inline]
     pub fn update_bodies(&mut self, new_bodies: Vec<Body>) {
         self.bodies = new_bodies;
+        self.time += self.params.dt;
+        kinematics::apply_trajectories(&mut self.bodies, &self.trajectories, self.time);
     }
 }
 
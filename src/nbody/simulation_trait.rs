@@ -7,6 +7,13 @@ pub trait Simulation {
 
     fn set_bodies(&mut self, bodies: Vec<Body>);
 
+    /// Keeps the bodies at the ascending indices `kept`, renumbered in that order. Backends
+    /// move per-body state such as trajectories along; wrappers pass it on.
+    fn retain_bodies(&mut self, kept: &[usize]) {
+        let bodies = self.get_bodies();
+        self.set_bodies(kept.iter().map(|&i| bodies[i]).collect());
+    }
+
     fn get_params(&self) -> &SimulationParams;
     fn set_params(&mut self, simulation_params: SimulationParams);
 }
@@ -11,11 +11,39 @@ pub trait NBodySimulator: Send {
 }
 
 pub mod utils {
-    use rand::Rng;
-    use crate::nbody::shader_types::nbody::Body;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use crate::nbody::diagnostics;
+    use crate::nbody::shader_types::nbody::{Body, SimulationParams};
 
+    pub use crate::nbody::initial_conditions::collision::CollisionBuilder;
+    pub use crate::nbody::initial_conditions::disks::{generate_kuzmin_disk, generate_uniform_disk};
+    pub use crate::nbody::initial_conditions::galaxy::GalaxyBuilder;
+    pub use crate::nbody::initial_conditions::imf::{assign_masses, InitialMassFunction, MassSpectrum};
+    pub use crate::nbody::initial_conditions::kepler::{OrbitalElements, PlanetarySystemBuilder};
+    pub use crate::nbody::initial_conditions::lattice::generate_perturbed_lattice;
+    pub use crate::nbody::initial_conditions::plummer::generate_plummer_projected;
+    pub use crate::nbody::initial_conditions::profiles::{
+        generate_hernquist_projected, generate_king_projected, generate_nfw_projected, king_concentration,
+    };
+    pub use crate::nbody::initial_conditions::zeldovich::generate_zeldovich;
+
+    /// Portable PRNG for reproducible initial conditions: the same seed gives the same
+    /// bodies on every platform and run.
+    pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+        ChaCha8Rng::seed_from_u64(seed)
+    }
+
+    /// Unseeded, different on every call. Use `generate_random_bodies_seeded` for experiments.
     pub fn generate_random_bodies(n: usize, mass: f32) -> Vec<Body> {
-        let mut rng = rand::rng();
+        generate_random_bodies_with_rng(&mut rand::rng(), n, mass)
+    }
+
+    pub fn generate_random_bodies_seeded(n: usize, mass: f32, seed: u64) -> Vec<Body> {
+        generate_random_bodies_with_rng(&mut seeded_rng(seed), n, mass)
+    }
+
+    pub fn generate_random_bodies_with_rng<R: Rng + ?Sized>(rng: &mut R, n: usize, mass: f32) -> Vec<Body> {
         (0..n)
             .map(|_| {
                 Body::new(
@@ -37,6 +65,15 @@ pub mod utils {
         ]
     }
 
+    /// Two equal masses on an exact circular orbit for `g_constant = 1`.
+    pub fn generate_circular_binary() -> Vec<Body> {
+        vec![
+            Body::new([0.0, 1.0], [5.0, 0.0], 100.0),
+            Body::new([0.0, -1.0], [-5.0, 0.0], 100.0),
+        ]
+    }
+
+    /// Bodies at rest on a ring. For a rotating disk in equilibrium use `GalaxyBuilder`.
     pub fn generate_circular_system(n: usize, radius: f32) -> Vec<Body> {
         (0..n)
             .map(|i| {
@@ -49,5 +86,64 @@ pub mod utils {
             })
             .collect()
     }
-}
 
+    /// Shifts positions and velocities into the centre-of-mass frame.
+    pub fn to_centre_of_mass_frame(bodies: &mut [Body]) {
+        let (position, velocity) = diagnostics::centre_of_mass(bodies);
+        for body in bodies.iter_mut() {
+            for k in 0..2 {
+                body.position[k] = (body.position[k] as f64 - position[k]) as f32;
+                body.velocity[k] = (body.velocity[k] as f64 - velocity[k]) as f32;
+            }
+        }
+    }
+
+    /// Scales velocities about the centre-of-mass velocity so that `T / |W| = q`
+    /// (0.5 is virial equilibrium).
+    pub fn scale_to_virial_ratio(bodies: &mut [Body], q: f64, params: &SimulationParams) {
+        let (_, com_velocity) = diagnostics::centre_of_mass(bodies);
+        let kinetic: f64 = bodies
+            .iter()
+            .map(|b| {
+                let dvx = b.velocity[0] as f64 - com_velocity[0];
+                let dvy = b.velocity[1] as f64 - com_velocity[1];
+                0.5 * b.mass as f64 * (dvx * dvx + dvy * dvy)
+            })
+            .sum();
+        assert!(kinetic > 0.0 || q == 0.0, "cannot scale a cold system to a non-zero virial ratio");
+
+        let target = q * diagnostics::potential_energy(bodies, params).abs();
+        let factor = if kinetic > 0.0 { (target / kinetic).sqrt() } else { 0.0 };
+        for body in bodies.iter_mut() {
+            for (v, com) in body.velocity.iter_mut().zip(com_velocity) {
+                *v = (com + factor * (*v as f64 - com)) as f32;
+            }
+        }
+    }
+
+    /// Rescales to Hénon units: centre-of-mass frame, G = M = 1 and E = -1/4, keeping the virial
+    /// ratio measured with the `g_constant` of `params`. Both the ratio and the new energy are
+    /// unsoftened, so simulate with `g_constant: 1.0` afterwards.
+    pub fn scale_to_henon_units(bodies: &mut [Body], params: &SimulationParams) {
+        to_centre_of_mass_frame(bodies);
+        let q = diagnostics::virial_ratio(bodies, &SimulationParams { epsilon: 0.0, ..*params });
+        assert!(q < 1.0, "an unbound system has no Hénon units");
+
+        let total = diagnostics::total_mass(bodies);
+        for body in bodies.iter_mut() {
+            body.mass = (body.mass as f64 / total) as f32;
+        }
+        let henon = SimulationParams { g_constant: 1.0, epsilon: 0.0, ..*params };
+        let kinetic = diagnostics::kinetic_energy(bodies);
+        let potential = diagnostics::potential_energy(bodies, &henon).abs();
+
+        // |W'| = 1 / (4 (1 - Q)) and T' = Q |W'| give E' = -1/4
+        let target_potential = 0.25 / (1.0 - q);
+        let length_factor = potential / target_potential;
+        let velocity_factor = if kinetic > 0.0 { (q * target_potential / kinetic).sqrt() } else { 1.0 };
+        for body in bodies.iter_mut() {
+            body.position = body.position.map(|c| (c as f64 * length_factor) as f32);
+            body.velocity = body.velocity.map(|c| (c as f64 * velocity_factor) as f32);
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Special functions shared by the kernels, wrappers and generators.
+//!
+//! `nbody.wgsl` carries its own `erfc_approx` with the same coefficients, since the shader
+//! can't call into Rust.
+
+/// Complementary error function, Abramowitz & Stegun 7.1.26 (|error| < 1.5e-7), for x >= 0.
+/// Evaluated directly rather than as `1 - erf` so small tails keep their relative accuracy.
+#[inline]
+pub fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    poly * (-x * x).exp()
+}
+
+/// Error function for any sign of `x`, from [`erfc`].
+#[inline]
+pub fn erf(x: f64) -> f64 {
+    (1.0 - erfc(x.abs())).copysign(x)
+}
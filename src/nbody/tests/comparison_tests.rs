@@ -5,10 +5,11 @@ use crate::nbody::tests::integration_tests::compare_bodies;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
 
 const EPSILON: f32 = 0.01; // Tolerance for floating point comparisons
+const SEED: u64 = 42;
 
 #[test]
 fn test_cpu_single_vs_cpu_rayon() {
-    let bodies = utils::generate_random_bodies(50, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(50, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim1 = CpuSingleThreaded::new(bodies.clone(), params);
@@ -26,7 +27,7 @@ fn test_cpu_single_vs_cpu_rayon() {
 #[test]
 fn test_cpu_single_vs_simd_single() {
     // Use a count divisible by 8 for optimal SIMD performance
-    let bodies = utils::generate_random_bodies(64, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(64, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim1 = CpuSingleThreaded::new(bodies.clone(), params);
@@ -43,7 +44,7 @@ fn test_cpu_single_vs_simd_single() {
 
 #[test]
 fn test_cpu_single_vs_simd_rayon() {
-    let bodies = utils::generate_random_bodies(64, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(64, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim1 = CpuSingleThreaded::new(bodies.clone(), params);
@@ -60,7 +61,7 @@ fn test_cpu_single_vs_simd_rayon() {
 
 #[test]
 fn test_simd_single_vs_simd_rayon() {
-    let bodies = utils::generate_random_bodies(64, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(64, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim1 = SimdSingleThreaded::new(bodies.clone(), params);
@@ -77,7 +78,7 @@ fn test_simd_single_vs_simd_rayon() {
 
 #[tokio::test]
 async fn test_cpu_single_vs_gpu() {
-    let bodies = utils::generate_random_bodies(11, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(11, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim1 = CpuSingleThreaded::new(bodies.clone(), params);
@@ -121,7 +122,7 @@ async fn test_gpu_basic() {
 #[test]
 fn test_all_implementations_consistency() {
     // Test with all CPU implementations
-    let bodies = utils::generate_random_bodies(64, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(64, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim_cpu_single = CpuSingleThreaded::new(bodies.clone(), params);
@@ -157,6 +158,7 @@ async fn test_exact_symmetry_gpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = GpuSimulator::new(bodies.clone(), params).await;
@@ -0,0 +1,132 @@
+// Cosmology tests - Friedmann factors, comoving motion and backend agreement
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::cosmology::{from_peculiar, to_peculiar, to_physical};
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::tests::integration_tests::compare_bodies;
+
+/// Unsoftened Ewald box, large enough that the single-body tests stay inside it.
+fn comoving_params() -> SimulationParams {
+    SimulationParams { epsilon: 0.0, box_size: 4.0, ewald: 1, ..SimulationParams::default() }
+}
+
+#[test]
+fn test_einstein_de_sitter_factors() {
+    let eds = Cosmology::einstein_de_sitter(1.0);
+    let (a0, a1) = (0.1f64, 0.5f64);
+
+    assert_relative_eq!(eds.cosmic_time(a1), 2.0 / 3.0 * a1.powf(1.5), max_relative = 1e-6);
+    assert_relative_eq!(eds.kick_factor(a0, a1), 2.0 * (a1.sqrt() - a0.sqrt()), max_relative = 1e-6);
+    assert_relative_eq!(eds.drift_factor(a0, a1), 2.0 * (1.0 / a0.sqrt() - 1.0 / a1.sqrt()), max_relative = 1e-6);
+}
+
+#[test]
+fn test_lambda_cdm_age() {
+    let lcdm = Cosmology::new(0.3, 0.7, 1.0);
+    let age = 2.0 / (3.0 * 0.7f64.sqrt()) * (0.7f64 / 0.3).sqrt().asinh();
+    assert_relative_eq!(lcdm.cosmic_time(1.0), age, max_relative = 1e-5);
+    assert_relative_eq!(lcdm.hubble(1.0), 1.0);
+    assert_relative_eq!(lcdm.omega_k(), 0.0);
+}
+
+#[test]
+fn test_free_particle_keeps_momentum() {
+    let eds = Cosmology::einstein_de_sitter(1.0);
+    let bodies = vec![Body::new([0.0, 0.0], [0.1, 0.0], 0.0)];
+    let mut sim = Comoving::new(CpuSingleThreaded::new(bodies, comoving_params()), eds, 0.1, 0.01);
+
+    sim.run_to(1.0);
+    assert_relative_eq!(sim.scale_factor(), 1.0);
+    assert_relative_eq!(sim.redshift(), 0.0);
+    let body = sim.get_bodies()[0];
+    assert_relative_eq!(body.velocity[0], 0.1, max_relative = 1e-6);
+    assert_relative_eq!(body.position[0], 0.1 * eds.drift_factor(0.1, 1.0) as f32, max_relative = 1e-4);
+    // Peculiar velocity decays as 1 / a
+    assert_relative_eq!(to_peculiar(&[body], 0.5)[0].velocity[0], 0.2, max_relative = 1e-6);
+}
+
+#[test]
+fn test_comoving_kick() {
+    let eds = Cosmology::einstein_de_sitter(1.0);
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0).with_motion(Motion::Fixed),
+        Body::new([1.0, 0.0], [0.0, 0.0], 0.0),
+    ];
+    let mut sim = Comoving::new(CpuSingleThreaded::new(bodies, comoving_params()), eds, 0.5, 0.01);
+
+    sim.step(1);
+    assert_relative_eq!(sim.scale_factor(), 0.51);
+    let acceleration = periodic::ewald_acceleration([-1.0, 0.0], 4.0, 0.0)[0];
+    let velocity = acceleration * eds.kick_factor(0.5, 0.51) as f32;
+    let body = sim.get_bodies()[1];
+    assert_relative_eq!(body.velocity[0], velocity, max_relative = 1e-4);
+    assert_relative_eq!(body.position[0], 1.0 + velocity * eds.drift_factor(0.5, 0.51) as f32, max_relative = 1e-6);
+    assert_relative_eq!(sim.get_params().g_constant, 1.0);
+}
+
+#[test]
+fn test_uniform_lattice_stays_put() {
+    // With Ewald summation a periodic lattice feels no net force against the mean density
+    let bodies: Vec<Body> = (0..16)
+        .map(|i| Body::new([-0.375 + 0.25 * (i % 4) as f32, -0.375 + 0.25 * (i / 4) as f32], [0.0, 0.0], 1.0 / 16.0))
+        .collect();
+    let params = SimulationParams { box_size: 1.0, ..comoving_params() };
+    let mut sim = Comoving::new(CpuSingleThreaded::new(bodies.clone(), params), Cosmology::einstein_de_sitter(1.0), 0.1, 0.01);
+
+    // The Ewald truncation residuals grow with the linear growth factor, here tenfold
+    sim.run_to(1.0);
+    compare_bodies(&bodies, &sim.get_bodies(), 1e-3);
+}
+
+#[test]
+fn test_velocity_conventions() {
+    let eds = Cosmology::einstein_de_sitter(2.0);
+    let bodies = vec![Body::new([1.0, -2.0], [0.0, 0.0], 1.0), Body::new([0.5, 0.5], [0.3, -0.1], 1.0)];
+    compare_bodies(&bodies, &from_peculiar(&to_peculiar(&bodies, 0.25), 0.25), 1e-6);
+
+    // A body at rest in comoving coordinates moves with the Hubble flow
+    let physical = to_physical(&bodies, &eds, 0.25);
+    let hubble = (eds.hubble(0.25) * 0.25) as f32;
+    assert_relative_eq!(physical[0].position[1], -0.5);
+    assert_relative_eq!(physical[0].velocity[0], hubble, max_relative = 1e-6);
+    assert_relative_eq!(physical[0].velocity[1], -2.0 * hubble, max_relative = 1e-6);
+    assert_relative_eq!(physical[1].velocity[0], 0.5 * hubble + 1.2, max_relative = 1e-6);
+}
+
+#[tokio::test]
+async fn test_comoving_backends_agree() {
+    let bodies = utils::generate_random_bodies_seeded(30, 0.01, 5);
+    let params = SimulationParams { box_size: 4.0, ewald: 1, ..SimulationParams::default() };
+    let lcdm = Cosmology::new(0.3, 0.7, 1.0);
+
+    let mut reference = Comoving::new(CpuSingleThreaded::new(bodies.clone(), params), lcdm, 0.5, 0.001);
+    let mut gpu = Comoving::new(GpuSimulator::new(bodies.clone(), params).await, lcdm, 0.5, 0.001);
+    let mut simd = Comoving::new(SimdMultiThreaded::new(bodies, params), lcdm, 0.5, 0.001);
+    reference.step(10);
+    gpu.step(10);
+    simd.step(10);
+
+    compare_bodies(&reference.get_bodies(), &gpu.get_bodies(), 1e-3);
+    compare_bodies(&reference.get_bodies(), &simd.get_bodies(), 1e-3);
+}
+
+#[test]
+#[should_panic(expected = "periodic box with Ewald summation")]
+fn test_comoving_rejects_minimum_image_box() {
+    let params = SimulationParams { ewald: 0, ..comoving_params() };
+    Comoving::new(CpuSingleThreaded::new(Vec::new(), params), Cosmology::einstein_de_sitter(1.0), 0.1, 0.01);
+}
+
+#[test]
+#[should_panic(expected = "periodic box with Ewald summation")]
+fn test_comoving_rejects_open_boundaries() {
+    let params = SimulationParams { box_size: 0.0, ..comoving_params() };
+    Comoving::new(CpuSingleThreaded::new(Vec::new(), params), Cosmology::einstein_de_sitter(1.0), 0.1, 0.01);
+}
+
+#[test]
+#[should_panic(expected = "inertial frame")]
+fn test_comoving_rejects_rotating_frame() {
+    let mut sim = Comoving::new(CpuSingleThreaded::new(Vec::new(), comoving_params()), Cosmology::einstein_de_sitter(1.0), 0.1, 0.01);
+    sim.set_params(SimulationParams { omega: 1.0, ..comoving_params() });
+}
@@ -60,6 +60,20 @@ fn test_energy_conservation_approximation() {
         "Energy changed too much: {} -> {}", initial_energy, final_energy);
 }
 
+#[test]
+fn test_diagnostics_energy_matches_reference() {
+    let bodies = utils::generate_circular_system(8, 1.0);
+    let params = SimulationParams { epsilon: 0.0, g_constant: 1.0, ..SimulationParams::default() };
+
+    let mut sim = CpuSingleThreaded::new(bodies, params);
+    for _ in 0..3 {
+        let bodies = sim.get_bodies();
+        let reference = calculate_total_energy(&bodies) as f64;
+        assert_relative_eq!(diagnostics::total_energy(&bodies, &params), reference, max_relative = 1e-5);
+        sim.step(5);
+    }
+}
+
 #[test]
 fn test_circular_system() {
     let bodies = utils::generate_circular_system(8, 1.0);
@@ -88,6 +102,7 @@ fn test_exact_symmetry_cpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
@@ -120,6 +135,7 @@ fn test_deterministic_gravity_calculation() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
@@ -147,6 +163,7 @@ fn test_inverse_square_law() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     // Test 1: Bodies with distance 1
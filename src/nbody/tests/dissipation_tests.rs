@@ -0,0 +1,125 @@
+// Dissipative force tests - exact kicks, operator splitting and orbital decay
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use std::sync::Arc;
+
+fn free_body(velocity: [f32; 2], mass: f32) -> Vec<Body> {
+    vec![Body::new([0.0, 0.0], velocity, mass)]
+}
+
+#[test]
+fn test_linear_drag_matches_exponential_decay() {
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let mut sim = Dissipative::new(
+        CpuSingleThreaded::new(free_body([3.0, -4.0], 1.0), params),
+        Arc::new(LinearDrag::new(0.5)),
+    );
+
+    sim.step(100);
+    sim.step(100);
+    let body = sim.get_bodies()[0];
+    let decay = (-0.5f32 * 2.0).exp();
+    assert_relative_eq!(body.velocity[0], 3.0 * decay, max_relative = 1e-5);
+    assert_relative_eq!(body.velocity[1], -4.0 * decay, max_relative = 1e-5);
+
+    // x(t) = v0 (1 - e^(-γt)) / γ, to second order in dt
+    assert_relative_eq!(body.position[0], 3.0 * (1.0 - decay) / 0.5, max_relative = 1e-4);
+}
+
+#[test]
+fn test_quadratic_drag_matches_analytic_speed() {
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let mut sim = Dissipative::new(
+        CpuSingleThreaded::new(free_body([0.0, 2.0], 1.0), params),
+        Arc::new(QuadraticDrag::new(0.3)),
+    );
+
+    sim.step(300);
+    let body = sim.get_bodies()[0];
+    assert_eq!(body.velocity[0], 0.0);
+    assert_relative_eq!(body.velocity[1], 2.0 / (1.0 + 0.3 * 2.0 * 3.0), max_relative = 1e-4);
+}
+
+#[test]
+fn test_dynamical_friction_slows_without_reversing() {
+    let friction = DynamicalFriction::new(1.0, 1.0, 1.0, 3.0);
+
+    // Heavier and slower bodies feel more drag; far above σ it falls off as 1/v²
+    assert!(friction.deceleration(2.0, 1.0) > friction.deceleration(1.0, 1.0));
+    assert!(friction.deceleration(1.0, 1.0) > friction.deceleration(1.0, 4.0));
+    let fast = friction.deceleration(1.0, 50.0) * 50.0 * 50.0;
+    assert_relative_eq!(fast, 4.0 * std::f32::consts::PI * 3.0, max_relative = 1e-4);
+    assert_eq!(friction.acceleration(&Body::new([0.0, 0.0], [0.0, 0.0], 1.0)), [0.0, 0.0]);
+    let stronger = DynamicalFriction::new(2.0, 1.0, 1.0, 3.0);
+    assert_relative_eq!(stronger.deceleration(1.0, 1.0), 4.0 * friction.deceleration(1.0, 1.0), max_relative = 1e-6);
+
+    let mut body = Body::new([0.0, 0.0], [0.1, 0.0], 100.0);
+    friction.kick(&mut body, 1.0);
+    assert_eq!(body.velocity, [0.0, 0.0]);
+}
+
+#[test]
+fn test_dynamical_friction_sinks_satellite() {
+    // Satellite on a circular orbit in a flat rotation curve halo
+    let bodies = free_body([0.0, 1.0], 0.01);
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let halo: Arc<dyn ExternalField> = Arc::new(LogarithmicField::new(1.0, 0.01, 1.0));
+    let radius = |bodies: &[Body]| bodies[0].position[0].hypot(bodies[0].position[1]);
+
+    let mut start = bodies.clone();
+    start[0].position = [2.0, 0.0];
+
+    let mut free = CpuSingleThreaded::new(start.clone(), params);
+    free.set_external_field(Some(halo.clone()));
+    let mut inner = CpuSingleThreaded::new(start, params);
+    inner.set_external_field(Some(halo));
+    let friction = DynamicalFriction::new(params.g_constant, 0.1, 0.7, 5.0);
+    let mut sinking = Dissipative::new(inner, Arc::new(friction));
+
+    free.step(2000);
+    sinking.step(2000);
+    assert_relative_eq!(radius(&free.get_bodies()), 2.0, epsilon = 5e-2);
+    assert!(radius(&sinking.get_bodies()) < 1.5);
+}
+
+#[test]
+fn test_composite_force_and_closure() {
+    let drag: Arc<dyn DissipativeForce> = Arc::new(LinearDrag::new(0.2));
+    let closure: Arc<dyn DissipativeForce> = Arc::new(ClosureForce(|b: &Body| [-0.2 * b.velocity[0], -0.2 * b.velocity[1]]));
+    let composite = CompositeForce(vec![drag.clone(), closure.clone()]);
+
+    let body = Body::new([0.0, 0.0], [1.0, 2.0], 1.0);
+    assert_eq!(drag.acceleration(&body), closure.acceleration(&body));
+    let a = composite.acceleration(&body);
+    assert_relative_eq!(a[0], -0.4);
+    assert_relative_eq!(a[1], -0.8);
+}
+
+#[test]
+fn test_zero_drag_is_transparent() {
+    let bodies = utils::generate_random_bodies_seeded(20, 0.01, 3);
+    let params = SimulationParams::default();
+
+    let mut plain = CpuSingleThreaded::new(bodies.clone(), params);
+    let mut wrapped = Dissipative::new(SimdSingleThreaded::new(bodies, params), Arc::new(LinearDrag::new(0.0)));
+    plain.step(10);
+    wrapped.step(10);
+    crate::nbody::tests::integration_tests::compare_bodies(&plain.get_bodies(), &wrapped.get_bodies(), 1e-3);
+}
+
+#[test]
+fn test_drag_skips_prescribed_bodies() {
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let bodies = vec![
+        Body::new([-50.0, 0.0], [1.0, 0.0], 0.0).with_motion(Motion::Kinematic),
+        Body::new([50.0, 0.0], [0.0, 1.0], 0.0),
+    ];
+    let mut sim = Dissipative::new(CpuSingleThreaded::new(bodies, params), Arc::new(LinearDrag::new(1.0)));
+
+    sim.step(100);
+    let bodies = sim.get_bodies();
+    assert_eq!(bodies[0].velocity, [1.0, 0.0]);
+    assert_relative_eq!(bodies[0].position[0], -49.0, max_relative = 1e-5);
+    assert_relative_eq!(bodies[1].velocity[1], (-1.0f32).exp(), max_relative = 1e-4);
+}
@@ -0,0 +1,191 @@
+// Escape monitor tests - energy and radius criteria, policies and id bookkeeping
+use crate::nbody::*;
+use crate::nbody::external_field::PointMassField;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use std::sync::Arc;
+
+/// Circular equal-mass binary plus a slow bound companion and a fast runaway.
+fn binary_with_runaway() -> Vec<Body> {
+    vec![
+        Body::new([-0.5, 0.0], [0.0, -0.5], 1.0),
+        Body::new([0.5, 0.0], [0.0, 0.5], 1.0),
+        Body::new([0.0, 3.0], [0.2, 0.0], 0.01),
+        Body::new([0.0, -2.0], [0.0, -5.0], 0.01),
+    ]
+}
+
+fn params() -> SimulationParams {
+    SimulationParams { dt: 0.001, ..SimulationParams::default() }
+}
+
+#[test]
+fn test_find_unbound_bodies() {
+    let monitor = EscapeMonitor::new(CpuSingleThreaded::new(binary_with_runaway(), params()), 10, EscapePolicy::Log);
+    assert_eq!(monitor.find_escapers(&binary_with_runaway()), vec![3]);
+
+    let by_radius = EscapeMonitor::new(CpuSingleThreaded::new(binary_with_runaway(), params()), 10, EscapePolicy::Log)
+        .radius(2.5)
+        .unbound(false);
+    assert_eq!(by_radius.find_escapers(&binary_with_runaway()), vec![2]);
+}
+
+#[test]
+fn test_collect_moves_escapers_out() {
+    let mut monitor =
+        EscapeMonitor::new(CpuSingleThreaded::new(binary_with_runaway(), params()), 50, EscapePolicy::Collect);
+
+    monitor.step(30);
+    assert_eq!(monitor.get_bodies().len(), 4);
+    monitor.step(30);
+
+    assert_eq!(monitor.get_bodies().len(), 3);
+    assert_eq!(monitor.ids(), &[0, 1, 2]);
+    assert_eq!(monitor.removed(), 1);
+    let escaper = &monitor.escapers()[0];
+    assert_eq!(escaper.id, 3);
+    assert_eq!(escaper.step, 50);
+    assert!((escaper.time - 0.05).abs() < 1e-6);
+    assert!(escaper.body.position[1] < -2.0);
+
+    // The rest stays bound and keeps running
+    monitor.step(200);
+    assert_eq!(monitor.get_bodies().len(), 3);
+    assert_eq!(monitor.escapers().len(), 1);
+}
+
+#[test]
+fn test_log_keeps_bodies_and_logs_once() {
+    let mut monitor =
+        EscapeMonitor::new(CpuSingleThreaded::new(binary_with_runaway(), params()), 10, EscapePolicy::Log);
+
+    monitor.step(100);
+    assert_eq!(monitor.get_bodies().len(), 4);
+    assert_eq!(monitor.escapers().len(), 1);
+    assert_eq!(monitor.escapers()[0].step, 10);
+    assert_eq!(monitor.removed(), 0);
+}
+
+#[test]
+fn test_remove_renumbers_ids() {
+    let mut bodies = binary_with_runaway();
+    bodies.insert(0, Body::new([0.0, 10.0], [0.0, 0.0], 0.01));
+    let mut monitor = EscapeMonitor::new(SimdSingleThreaded::new(bodies, params()), 5, EscapePolicy::Remove)
+        .radius(5.0);
+
+    monitor.step(5);
+    assert!(monitor.escapers().is_empty());
+    assert_eq!(monitor.removed(), 2);
+    assert_eq!(monitor.ids(), &[1, 2, 3]);
+    assert_eq!(monitor.get_bodies()[2].mass, 0.01);
+}
+
+#[test]
+fn test_removal_from_outside_keeps_ids() {
+    let mut bodies = binary_with_runaway();
+    bodies.insert(0, Body::new([0.0, 10.0], [0.0, 0.0], 0.01));
+    let logging = EscapeMonitor::new(CpuSingleThreaded::new(bodies, params()), 10, EscapePolicy::Log);
+    let mut monitor = EscapeMonitor::new(logging, 5, EscapePolicy::Remove).radius(5.0).unbound(false);
+
+    monitor.step(10);
+    assert_eq!(monitor.ids(), &[1, 2, 3, 4]);
+    assert_eq!(monitor.inner().ids(), &[1, 2, 3, 4]);
+    let escapers = monitor.inner().escapers();
+    assert_eq!(escapers.len(), 1);
+    assert_eq!(escapers[0].id, 4);
+}
+
+#[test]
+fn test_driven_bodies_never_escape() {
+    let mut bodies = binary_with_runaway();
+    bodies[3] = bodies[3].with_motion(Motion::Kinematic);
+    let monitor = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Remove);
+    assert!(monitor.find_escapers(&bodies).is_empty());
+}
+
+/// Massless perturber on a circle of radius 10, far from the binary.
+fn slow_perturber() -> Trajectory {
+    Arc::new(|t: f32| {
+        let (sin, cos) = (0.1 * t).sin_cos();
+        ([10.0 * cos, 10.0 * sin], [-sin, cos])
+    })
+}
+
+fn with_perturber() -> Vec<Body> {
+    let mut bodies = binary_with_runaway();
+    bodies.push(Body::new([10.0, 0.0], [0.0, 1.0], 0.0));
+    bodies
+}
+
+#[test]
+fn test_remove_carries_trajectories_along() {
+    let mut sim = CpuSingleThreaded::new(with_perturber(), params());
+    sim.set_trajectory(4, slow_perturber());
+    let mut monitor = EscapeMonitor::new(sim, 5, EscapePolicy::Remove);
+
+    monitor.step(20);
+    assert_eq!(monitor.ids(), &[0, 1, 2, 4]);
+    let bodies = monitor.get_bodies();
+    assert_eq!(bodies[3].motion(), Motion::Kinematic);
+    assert_eq!(bodies[3].position, slow_perturber()(monitor.inner().time()).0);
+    assert_eq!(monitor.inner().time(), 20.0 * params().dt);
+}
+
+#[tokio::test]
+async fn test_remove_carries_trajectories_along_gpu() {
+    let mut sim = GpuSimulator::new(with_perturber(), params()).await;
+    sim.set_trajectory(4, slow_perturber());
+    let mut monitor = EscapeMonitor::new(sim, 5, EscapePolicy::Remove);
+
+    monitor.step(20);
+    assert_eq!(monitor.ids(), &[0, 1, 2, 4]);
+    let bodies = monitor.get_bodies();
+    assert_eq!(bodies.len(), 4);
+    assert_eq!(bodies[3].position, slow_perturber()(monitor.inner().time()).0);
+}
+
+#[test]
+fn test_energy_test_includes_field_and_species() {
+    // Two light bodies held only by the external point mass
+    let bodies = vec![Body::new([1.0, 0.0], [0.0, 1.0], 1e-6), Body::new([-1.0, 0.0], [0.0, -1.0], 1e-6)];
+    let plain = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Log);
+    assert_eq!(plain.find_escapers(&bodies), vec![0, 1]);
+    let with_field = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Log)
+        .external_field(Arc::new(PointMassField::new(1.0, 0.0)));
+    assert!(with_field.find_escapers(&bodies).is_empty());
+
+    // A tracer bound to a star, unless tracers ignore stars
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0),
+        Body::new([1.0, 0.0], [0.0, 1.2], 1e-6).with_species(species::TRACER),
+    ];
+    let coupled = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Log);
+    assert!(coupled.find_escapers(&bodies).is_empty());
+    let decoupled = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Log)
+        .species_table(SpeciesTable::uniform(1e-6).ignores(species::TRACER, species::STAR));
+    assert_eq!(decoupled.find_escapers(&bodies), vec![1]);
+}
+
+#[test]
+fn test_energy_test_uses_nearest_image() {
+    let bodies = vec![Body::new([-1.9, 0.0], [0.0, 0.0], 1.0), Body::new([1.9, 0.0], [0.0, 1.5], 1e-6)];
+    let open = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params()), 1, EscapePolicy::Log);
+    assert_eq!(open.find_escapers(&bodies), vec![1]);
+
+    let boxed = SimulationParams { box_size: 4.0, ..params() };
+    let periodic = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), boxed), 1, EscapePolicy::Log);
+    assert!(periodic.find_escapers(&bodies).is_empty());
+
+    let ewald = SimulationParams { ewald: 1, ..boxed };
+    let by_radius = EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), ewald), 1, EscapePolicy::Log);
+    assert!(by_radius.find_escapers(&bodies).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "no Ewald potential")]
+fn test_energy_test_rejects_ewald() {
+    let bodies = binary_with_runaway();
+    let params = SimulationParams { box_size: 10.0, ewald: 1, ..params() };
+    EscapeMonitor::new(CpuSingleThreaded::new(bodies.clone(), params), 1, EscapePolicy::Log)
+        .unbound(true)
+        .find_escapers(&bodies);
+}
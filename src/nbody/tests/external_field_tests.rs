@@ -0,0 +1,116 @@
+// External field tests - analytic fields, orbits and backend agreement
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::tests::integration_tests::compare_bodies;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use std::sync::Arc;
+
+const SEED: u64 = 42;
+
+fn galaxy_field() -> Arc<dyn ExternalField> {
+    let mut disk = MiyamotoNagaiField::new(1.0, 0.5, 0.1);
+    disk.centre = [0.2, -0.1];
+    Arc::new(CompositeField(vec![
+        Arc::new(PointMassField::new(0.3, 0.05)),
+        Arc::new(disk),
+        Arc::new(LogarithmicField::new(0.8, 1.0, 0.9)),
+    ]))
+}
+
+#[test]
+fn test_analytic_fields() {
+    let point = PointMassField::new(4.0, 0.0);
+    let a = point.acceleration([0.0, 2.0]);
+    assert_relative_eq!(a[0], 0.0);
+    assert_relative_eq!(a[1], -1.0, max_relative = 1e-6);
+
+    // In its midplane Miyamoto-Nagai is a point mass softened by a + b
+    let disk = MiyamotoNagaiField::new(4.0, 0.3, 0.2);
+    let softened = PointMassField::new(4.0, 0.5);
+    assert_eq!(disk.acceleration([1.0, 0.7]), softened.acceleration([1.0, 0.7]));
+
+    // Flat rotation curve far outside the core, stronger pull along the flattened axis
+    let halo = LogarithmicField::new(2.0, 0.1, 0.8);
+    let a = halo.acceleration([50.0, 0.0]);
+    assert_relative_eq!((-a[0] * 50.0).sqrt(), 2.0, max_relative = 1e-4);
+    assert!(halo.acceleration([0.0, 1.0])[1].abs() > halo.acceleration([1.0, 0.0])[0].abs());
+
+    let closure = ClosureField(|p: [f32; 2]| [-p[0], -p[1]]);
+    assert_eq!(closure.acceleration([1.0, -2.0]), [-1.0, 2.0]);
+    assert!(closure.gpu_terms().is_none());
+    assert_eq!(galaxy_field().gpu_terms().map(|t| t.len()), Some(3));
+}
+
+#[test]
+fn test_circular_orbit_in_point_mass_field() {
+    let bodies = vec![Body::new([1.0, 0.0], [0.0, 1.0], 1e-6)];
+    let params = SimulationParams { dt: 0.001, ..SimulationParams::default() };
+    let mut sim = CpuSingleThreaded::new(bodies, params);
+    sim.set_external_field(Some(Arc::new(PointMassField::new(1.0, 0.0))));
+
+    sim.step(6283);
+    let result = sim.get_bodies();
+    let radius = result[0].position[0].hypot(result[0].position[1]);
+    assert_relative_eq!(radius, 1.0, epsilon = 1e-2);
+    assert_relative_eq!(result[0].position[0], 1.0, epsilon = 2e-2);
+    assert_relative_eq!(result[0].position[1], 0.0, epsilon = 2e-2);
+}
+
+#[test]
+fn test_external_field_backends_agree() {
+    let bodies = utils::generate_random_bodies_seeded(37, 0.01, SEED);
+    let params = SimulationParams::default();
+    let field = galaxy_field();
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.set_external_field(Some(field.clone()));
+    reference.step(10);
+    let expected = reference.get_bodies();
+
+    let mut rayon = CpuMultiThreaded::new(bodies.clone(), params);
+    rayon.set_external_field(Some(field.clone()));
+    let mut simd = SimdSingleThreaded::new(bodies.clone(), params);
+    simd.set_external_field(Some(field.clone()));
+    let mut simd_rayon = SimdMultiThreaded::new(bodies.clone(), params);
+    simd_rayon.set_external_field(Some(field.clone()));
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.clone());
+    aligned.set_params(params);
+    aligned.set_external_field(Some(field.clone()));
+
+    let mut sims: Vec<Box<dyn Simulation>> =
+        vec![Box::new(rayon), Box::new(simd), Box::new(simd_rayon), Box::new(aligned)];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        compare_bodies(&expected, &sim.get_bodies(), 1e-3);
+    }
+
+    // The same field as a closure
+    let mut closure = CpuSingleThreaded::new(bodies, params);
+    let inner = field.clone();
+    closure.set_external_field(Some(Arc::new(ClosureField(move |p| inner.acceleration(p)))));
+    closure.step(10);
+    assert_eq!(closure.get_bodies(), expected);
+}
+
+#[tokio::test]
+async fn test_external_field_gpu_matches_cpu() {
+    let bodies = utils::generate_random_bodies_seeded(37, 0.01, SEED);
+    let params = SimulationParams::default();
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    cpu.set_external_field(Some(galaxy_field()));
+    let mut gpu = GpuSimulator::new(bodies, params).await;
+    gpu.set_external_field(Some(galaxy_field()));
+
+    cpu.step(10);
+    gpu.step(10);
+    compare_bodies(&cpu.get_bodies(), &gpu.get_bodies(), 1e-3);
+}
+
+#[tokio::test]
+#[should_panic(expected = "no analytic form")]
+async fn test_gpu_rejects_closure_field() {
+    let bodies = utils::generate_random_bodies_seeded(8, 0.01, SEED);
+    let mut gpu = GpuSimulator::new(bodies, SimulationParams::default()).await;
+    gpu.set_external_field(Some(Arc::new(ClosureField(|_| [0.0, 0.0]))));
+}
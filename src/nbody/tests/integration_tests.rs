@@ -3,10 +3,12 @@ use crate::nbody::*;
 use approx::assert_relative_eq;
 use crate::nbody::shader_types::nbody::{Body, SimulationParams};
 
+const SEED: u64 = 42;
+
 pub fn compare_bodies(bodies1: &[Body], bodies2: &[Body], tolerance: f32) {
     assert_eq!(bodies1.len(), bodies2.len(), "Number of bodies doesn't match");
 
-    for (_, (b1, b2)) in bodies1.iter().zip(bodies2.iter()).enumerate() {
+    for (b1, b2) in bodies1.iter().zip(bodies2.iter()) {
         assert_relative_eq!(b1.position[0], b2.position[0], epsilon = tolerance,
             max_relative = tolerance);
         assert_relative_eq!(b1.position[1], b2.position[1], epsilon = tolerance,
@@ -24,7 +26,7 @@ pub fn compare_bodies(bodies1: &[Body], bodies2: &[Body], tolerance: f32) {
 
 #[test]
 fn test_set_params_cpu_single() {
-    let bodies = utils::generate_random_bodies(10, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(10, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
@@ -38,6 +40,7 @@ fn test_set_params_cpu_single() {
         dt: 0.032,  // Double timestep
         epsilon: 1e-5,
         g_constant: 2.0,  // Double gravity
+        ..SimulationParams::default()
     };
     sim.set_params(new_params);
 
@@ -57,7 +60,7 @@ fn test_set_params_cpu_single() {
 
 #[tokio::test]
 async fn test_set_params_gpu() {
-    let bodies = utils::generate_random_bodies(10, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(10, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim = GpuSimulator::new(bodies.clone(), params).await;
@@ -71,6 +74,7 @@ async fn test_set_params_gpu() {
         dt: 0.032,  // Double timestep
         epsilon: 1e-5,
         g_constant: 2.0,  // Double gravity
+        ..SimulationParams::default()
     };
     sim.set_params(new_params);
 
@@ -92,7 +96,7 @@ async fn test_set_params_gpu() {
 
 #[test]
 fn test_set_bodies_cpu_single() {
-    let bodies = utils::generate_random_bodies(10, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(10, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
@@ -120,7 +124,7 @@ fn test_set_bodies_cpu_single() {
 
 #[tokio::test]
 async fn test_set_bodies_gpu() {
-    let bodies = utils::generate_random_bodies(10, 100.0);
+    let bodies = utils::generate_random_bodies_seeded(10, 100.0, SEED);
     let params = SimulationParams::default();
 
     let mut sim = GpuSimulator::new(bodies.clone(), params).await;
@@ -146,6 +150,22 @@ async fn test_set_bodies_gpu() {
     assert_ne!(bodies_after_step[1].position, new_bodies[1].position);
 }
 
+#[tokio::test]
+async fn test_set_bodies_gpu_grows_buffers() {
+    let params = SimulationParams::default();
+    let mut sim = GpuSimulator::new(utils::generate_two_body_system(), params).await;
+    sim.step(1);
+
+    let mut new_bodies = utils::generate_random_bodies_seeded(40, 100.0, SEED);
+    new_bodies[3].mass = 0.0;
+    sim.set_bodies(new_bodies.clone());
+    sim.step(5);
+
+    let mut reference = CpuSingleThreaded::new(new_bodies, params);
+    reference.step(5);
+    compare_bodies(&reference.get_bodies(), &sim.get_bodies(), 1e-3);
+}
+
 // ========== Deterministic Tests ==========
 
 #[test]
@@ -161,6 +181,7 @@ fn test_set_params_deterministic_cpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
@@ -181,6 +202,7 @@ fn test_set_params_deterministic_cpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 2.0,  // Double force
+        ..SimulationParams::default()
     };
     sim.set_params(new_params);
 
@@ -208,6 +230,7 @@ async fn test_set_params_deterministic_gpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = GpuSimulator::new(bodies.clone(), params).await;
@@ -228,6 +251,7 @@ async fn test_set_params_deterministic_gpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 2.0,
+        ..SimulationParams::default()
     };
     sim.set_params(new_params);
 
@@ -255,6 +279,7 @@ fn test_set_bodies_deterministic_cpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = CpuSingleThreaded::new(initial_bodies.clone(), params);
@@ -301,6 +326,7 @@ async fn test_set_bodies_deterministic_gpu() {
         dt: 0.1,
         epsilon: 0.0,
         g_constant: 1.0,
+        ..SimulationParams::default()
     };
 
     let mut sim = GpuSimulator::new(initial_bodies.clone(), params).await;
@@ -349,6 +375,7 @@ async fn test_params_synchronization_gpu() {
         dt: 0.032,
         epsilon: params.epsilon,
         g_constant: 5.0,  // Very different
+        ..SimulationParams::default()
     };
     sim2.set_params(modified_params);
 
@@ -0,0 +1,298 @@
+// Import/export tests - round trips and error reporting
+use crate::nbody::io::{csv, gadget, json, npy, vtk, IoError};
+use crate::nbody::simd_alligned_core::SimdAlignedNBodyCore;
+use crate::nbody::species;
+use crate::nbody::shader_types::nbody::Body;
+use crate::nbody::tests::integration_tests::compare_bodies;
+
+#[test]
+fn test_csv_round_trip() {
+    let bodies = vec![
+        Body::new([0.5, -1.25], [0.1, 0.2], 100.0),
+        Body::new([-3.0, 2.0], [0.0, -0.75], 1.5),
+    ];
+
+    let mut buffer = Vec::new();
+    csv::write_csv(&mut buffer, &bodies).unwrap();
+    let read = csv::read_csv(buffer.as_slice()).unwrap();
+
+    compare_bodies(&read, &bodies, 1e-6);
+}
+
+#[test]
+fn test_csv_column_order_and_id_sorting() {
+    let input = "id, mass, x, y, vx, vy\n\n2, 3.0, 2.0, 0.0, 0.0, 0.0\n0, 1.0, 0.0, 0.0, 0.0, 0.0\n1, 2.0, 1.0, 0.0, 0.0, 0.0\n";
+    let bodies = csv::read_csv(input.as_bytes()).unwrap();
+
+    assert_eq!(bodies.len(), 3);
+    for (i, body) in bodies.iter().enumerate() {
+        assert_eq!(body.position[0], i as f32);
+        assert_eq!(body.mass, i as f32 + 1.0);
+    }
+}
+
+#[test]
+fn test_csv_reports_line_numbers() {
+    let input = "x,y,vx,vy,mass\n0,0,0,0,1\n0,abc,0,0,1\n";
+    match csv::read_csv(input.as_bytes()) {
+        Err(IoError::Parse { line, message }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("'y'"), "unexpected message: {}", message);
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+
+    let input = "x,y,vx,vy,mass\n0,0,0,0,1\n\n0,0,0,1\n";
+    match csv::read_csv(input.as_bytes()) {
+        Err(IoError::Parse { line, .. }) => assert_eq!(line, 4),
+        other => panic!("expected parse error, got {:?}", other),
+    }
+
+    let input = "x,y,vx,mass\n";
+    match csv::read_csv(input.as_bytes()) {
+        Err(IoError::Parse { line, message }) => {
+            assert_eq!(line, 1);
+            assert!(message.contains("'vy'"), "unexpected message: {}", message);
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_json_round_trip() {
+    let bodies = vec![
+        Body::new([0.5, -1.25], [0.1, 0.2], 100.0),
+        Body::new([-3.0, 2.0], [0.0, -0.75], 1.5),
+    ];
+
+    let mut buffer = Vec::new();
+    json::write_json(&mut buffer, &bodies).unwrap();
+    let read = json::read_json(buffer.as_slice()).unwrap();
+
+    compare_bodies(&read, &bodies, 1e-6);
+}
+
+#[test]
+fn test_json_without_ids_and_errors() {
+    let input = r#"[{"x": 1, "y": 2, "vx": 0, "vy": 0, "mass": 5}]"#;
+    let bodies = json::read_json(input.as_bytes()).unwrap();
+    assert_eq!(bodies, vec![Body::new([1.0, 2.0], [0.0, 0.0], 5.0)]);
+
+    let input = "[\n  {\"x\": 1, \"y\": 2, \"vx\": 0, \"vy\": 0, \"mass\": 5},\n  {\"x\": 1, \"y\": 2, \"vx\": 0, \"mass\": 5}\n]";
+    match json::read_json(input.as_bytes()) {
+        Err(IoError::Parse { line, message }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("vy"), "unexpected message: {}", message);
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_species_round_trip_and_range_check() {
+    let bodies = vec![
+        Body::new([0.5, -1.25], [0.1, 0.2], 100.0),
+        Body::new([-3.0, 2.0], [0.0, -0.75], 1.5).with_species(species::GAS),
+    ];
+
+    let mut buffer = Vec::new();
+    csv::write_csv(&mut buffer, &bodies).unwrap();
+    assert_eq!(csv::read_csv(buffer.as_slice()).unwrap(), bodies);
+    let mut buffer = Vec::new();
+    json::write_json(&mut buffer, &bodies).unwrap();
+    assert_eq!(json::read_json(buffer.as_slice()).unwrap(), bodies);
+
+    let input = "x,y,vx,vy,mass,species\n0,0,0,0,1,1\n0,0,0,0,1,7\n";
+    match csv::read_csv(input.as_bytes()) {
+        Err(IoError::Parse { line, message }) => {
+            assert_eq!(line, 3);
+            assert!(message.contains("species"), "unexpected message: {}", message);
+        }
+        other => panic!("expected parse error, got {:?}", other),
+    }
+    let input = r#"[{"x": 1, "y": 2, "vx": 0, "vy": 0, "mass": 5, "species": 4}]"#;
+    assert!(matches!(json::read_json(input.as_bytes()), Err(IoError::Invalid(_))));
+}
+
+fn npy_header_len(bytes: &[u8]) -> usize {
+    assert_eq!(&bytes[..6], b"\x93NUMPY");
+    assert_eq!(&bytes[6..8], &[1, 0]);
+    10 + u16::from_le_bytes([bytes[8], bytes[9]]) as usize
+}
+
+fn npy_f32_data(bytes: &[u8]) -> Vec<f32> {
+    bytes[npy_header_len(bytes)..]
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+#[test]
+fn test_npy_positions_layout() {
+    let bodies = vec![
+        Body::new([1.0, 2.0], [0.0, 0.0], 1.0),
+        Body::new([3.0, 4.0], [0.0, 0.0], 1.0),
+        Body::new([5.0, 6.0], [0.0, 0.0], 1.0),
+    ];
+
+    let mut buffer = Vec::new();
+    npy::write_npy_positions(&mut buffer, &bodies).unwrap();
+
+    let header_len = npy_header_len(&buffer);
+    assert_eq!(header_len % 64, 0, "data must start 64-byte aligned");
+    let header = std::str::from_utf8(&buffer[10..header_len]).unwrap();
+    assert!(header.contains("'descr': '<f4'"));
+    assert!(header.contains("'fortran_order': False"));
+    assert!(header.contains("'shape': (3, 2)"));
+    assert!(header.ends_with('\n'));
+    assert_eq!(npy_f32_data(&buffer), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    let mut buffer = Vec::new();
+    npy::write_npy_masses(&mut buffer, &bodies).unwrap();
+    let header = std::str::from_utf8(&buffer[10..npy_header_len(&buffer)]).unwrap();
+    assert!(header.contains("'shape': (3,)"));
+}
+
+#[test]
+fn test_npz_bundle_from_bodies_and_columns() {
+    let bodies = vec![
+        Body::new([1.0, 2.0], [0.5, -0.5], 10.0),
+        Body::new([3.0, 4.0], [1.5, -1.5], 20.0),
+    ];
+
+    let mut from_bodies = Vec::new();
+    npy::write_npz(&mut from_bodies, &bodies).unwrap();
+
+    let core = SimdAlignedNBodyCore::new(bodies.clone());
+    let mut from_columns = Vec::new();
+    npy::write_npz_columns(&mut from_columns, &core).unwrap();
+
+    for archive in [&from_bodies, &from_columns] {
+        assert_eq!(&archive[..4], &[0x50, 0x4b, 0x03, 0x04]);
+        let end = &archive[archive.len() - 22..];
+        assert_eq!(&end[..4], &[0x50, 0x4b, 0x05, 0x06]);
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 3);
+        for name in ["positions.npy", "velocities.npy", "masses.npy"] {
+            assert!(archive.windows(name.len()).any(|w| w == name.as_bytes()), "missing {}", name);
+        }
+    }
+
+    // Columns are stored Fortran-ordered: all x, then all y
+    let header = "'fortran_order': True, 'shape': (2, 2)";
+    assert!(from_columns.windows(header.len()).any(|w| w == header.as_bytes()));
+    assert_eq!(from_bodies.len(), from_columns.len());
+}
+
+#[test]
+fn test_vtp_point_cloud() {
+    let bodies = vec![
+        Body::new([1.0, 2.0], [0.5, -0.5], 10.0),
+        Body::new([3.0, 4.0], [1.5, -1.5], 20.0),
+    ];
+
+    let mut buffer = Vec::new();
+    vtk::write_vtp(&mut buffer, &bodies).unwrap();
+    let xml = String::from_utf8(buffer).unwrap();
+
+    assert!(xml.contains(r#"NumberOfPoints="2" NumberOfVerts="2""#));
+    assert!(xml.contains(r#"Name="velocity" NumberOfComponents="3""#));
+    assert!(xml.contains(r#"Name="mass""#));
+    assert!(xml.contains("3 4 0"), "positions are padded with z = 0");
+    assert!(xml.contains("1.5 -1.5 0"));
+    assert!(xml.trim_end().ends_with("</VTKFile>"));
+}
+
+#[test]
+fn test_vtk_series_writes_pvd_index() {
+    let dir = std::env::temp_dir().join(format!("nbody_vtk_series_{}", std::process::id()));
+    let bodies = vec![Body::new([0.0, 0.0], [0.0, 0.0], 1.0)];
+
+    let mut series = vtk::VtkSeries::new(&dir, "run").unwrap();
+    let first = series.write_snapshot(0.0, &bodies).unwrap();
+    let second = series.write_snapshot(0.5, &bodies).unwrap();
+
+    assert_eq!(series.len(), 2);
+    assert!(first.exists() && second.exists());
+    let pvd = std::fs::read_to_string(series.pvd_path()).unwrap();
+    assert!(pvd.contains(r#"timestep="0" group="" part="0" file="run_000000.vtp""#));
+    assert!(pvd.contains(r#"timestep="0.5" group="" part="0" file="run_000001.vtp""#));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_gadget_round_trip_both_formats() {
+    let bodies = vec![
+        Body::new([1.0, 2.0], [0.5, -0.5], 2.0),
+        Body::new([3.0, 4.0], [1.5, -1.5], 2.0),
+        Body::new([-1.0, 0.5], [0.0, 0.25], 2.0),
+    ];
+    let snapshot = gadget::GadgetSnapshot::from_bodies(bodies.clone(), 1);
+
+    for format in [gadget::GadgetFormat::Format1, gadget::GadgetFormat::Format2] {
+        let mut buffer = Vec::new();
+        gadget::write_gadget(&mut buffer, &snapshot, format).unwrap();
+        let read = gadget::read_gadget(buffer.as_slice()).unwrap();
+
+        compare_bodies(&read.bodies, &bodies, 1e-6);
+        assert_eq!(read.ids, vec![0, 1, 2]);
+        assert_eq!(read.particle_types, vec![1, 1, 1]);
+        assert_eq!(read.header.npart, [0, 3, 0, 0, 0, 0]);
+        // Equal masses go into the header mass table, no MASS block needed
+        assert_eq!(read.header.mass_table[1], 2.0);
+    }
+}
+
+#[test]
+fn test_gadget_mixed_types_and_mass_block() {
+    let mut snapshot = gadget::GadgetSnapshot::from_bodies(
+        vec![
+            Body::new([0.0, 0.0], [0.0, 0.0], 5.0),
+            Body::new([1.0, 0.0], [0.0, 0.0], 1.0),
+            Body::new([2.0, 0.0], [0.0, 0.0], 3.0),
+            Body::new([3.0, 0.0], [0.0, 0.0], 1.0),
+        ],
+        1,
+    );
+    snapshot.particle_types = vec![4, 1, 4, 1];
+    snapshot.ids = vec![10, 11, 12, 13];
+
+    let mut buffer = Vec::new();
+    gadget::write_gadget(&mut buffer, &snapshot, gadget::GadgetFormat::Format1).unwrap();
+    let read = gadget::read_gadget(buffer.as_slice()).unwrap();
+
+    // Written sorted by type: type 1 shares one mass, type 4 needs individual masses
+    assert_eq!(read.particle_types, vec![1, 1, 4, 4]);
+    assert_eq!(read.ids, vec![11, 13, 10, 12]);
+    assert_eq!(read.header.mass_table[1], 1.0);
+    assert_eq!(read.header.mass_table[4], 0.0);
+    let masses: Vec<f32> = read.bodies.iter().map(|b| b.mass).collect();
+    assert_eq!(masses, vec![1.0, 1.0, 5.0, 3.0]);
+    assert_eq!(read.bodies[3].position, [2.0, 0.0]);
+}
+
+#[test]
+fn test_gadget_rejects_garbage() {
+    let garbage = [0x42u8; 64];
+    assert!(matches!(gadget::read_gadget(garbage.as_slice()), Err(IoError::Invalid(_))));
+
+    let snapshot = gadget::GadgetSnapshot::from_bodies(vec![Body::new([0.0, 0.0], [0.0, 0.0], 1.0)], 1);
+    let mut buffer = Vec::new();
+    gadget::write_gadget(&mut buffer, &snapshot, gadget::GadgetFormat::Format1).unwrap();
+    buffer.truncate(buffer.len() - 10);
+    assert!(gadget::read_gadget(buffer.as_slice()).is_err());
+}
+
+#[test]
+fn test_gadget_rejects_oversized_record_marker() {
+    let snapshot = gadget::GadgetSnapshot::from_bodies(vec![Body::new([0.0, 0.0], [0.0, 0.0], 1.0)], 1);
+    let mut buffer = Vec::new();
+    gadget::write_gadget(&mut buffer, &snapshot, gadget::GadgetFormat::Format1).unwrap();
+
+    // The POS marker follows the 256-byte header record
+    buffer[264..268].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+    match gadget::read_gadget(buffer.as_slice()) {
+        Err(IoError::Invalid(message)) => assert!(message.contains("POS record claims"), "{}", message),
+        other => panic!("expected an invalid POS record, got {:?}", other.map(|s| s.bodies.len())),
+    }
+}
@@ -0,0 +1,216 @@
+// Kinematics tests - fixed and kinematic bodies, trajectories and backend agreement
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::tests::integration_tests::compare_bodies;
+use std::sync::Arc;
+
+fn pinned_system() -> Vec<Body> {
+    let mut bodies = utils::generate_random_bodies_seeded(20, 0.01, 9);
+    bodies[0] = Body::new([0.0, 0.0], [0.3, 0.0], 5.0).with_motion(Motion::Fixed);
+    bodies[1] = Body::new([0.5, 0.5], [-0.2, 0.1], 2.0).with_motion(Motion::Kinematic);
+    bodies
+}
+
+/// Perturber on a circle of radius 2 with angular velocity 0.5.
+fn perturber() -> Trajectory {
+    Arc::new(|t: f32| {
+        let (sin, cos) = (0.5 * t).sin_cos();
+        ([2.0 * cos, 2.0 * sin], [-sin, cos])
+    })
+}
+
+#[test]
+fn test_motion_flag_round_trip() {
+    let body = Body::new([1.0, 2.0], [3.0, 4.0], 5.0);
+    assert_eq!(body.motion(), Motion::Free);
+    for motion in [Motion::Fixed, Motion::Kinematic, Motion::Free] {
+        let flagged = body.with_motion(motion);
+        assert_eq!(flagged.motion(), motion);
+        assert_eq!(flagged.position, body.position);
+        assert_eq!(flagged.mass, body.mass);
+    }
+}
+
+#[test]
+fn test_unknown_motion_flag_is_free() {
+    let mut body = Body::new([1.0, 0.0], [0.0, 1.0], 1.0);
+    body.padding0 = 7u32.to_ne_bytes();
+    assert_eq!(body.motion(), Motion::Free);
+
+    let centre = Body::new([0.0, 0.0], [0.0, 0.0], 1.0);
+    let mut flagged = CpuSingleThreaded::new(vec![centre, body], SimulationParams::default());
+    let mut plain = CpuSingleThreaded::new(vec![centre, Body::new([1.0, 0.0], [0.0, 1.0], 1.0)], SimulationParams::default());
+    flagged.step(10);
+    plain.step(10);
+    compare_bodies(&plain.get_bodies(), &flagged.get_bodies(), 1e-6);
+}
+
+#[test]
+fn test_set_bodies_drops_stale_trajectories() {
+    let bodies = pinned_system();
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.clone());
+    aligned.set_trajectory(2, perturber());
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), SimulationParams::default());
+    cpu.set_trajectory(2, perturber());
+    let mut sims: Vec<Box<dyn Simulation>> = vec![Box::new(cpu), Box::new(aligned)];
+
+    for sim in sims.iter_mut() {
+        // Handing the same bodies back keeps the trajectory
+        sim.set_bodies(sim.get_bodies());
+        sim.step(5);
+        assert_eq!(sim.get_bodies()[2].position, perturber()(5.0 * SimulationParams::default().dt).0);
+
+        // Its index is gone, then taken by a free body
+        sim.set_bodies(bodies[..2].to_vec());
+        sim.step(5);
+        sim.set_bodies(utils::generate_random_bodies_seeded(4, 0.01, 3));
+        sim.step(5);
+        assert_eq!(sim.get_bodies()[2].motion(), Motion::Free);
+        assert_ne!(sim.get_bodies()[2].position, perturber()(15.0 * SimulationParams::default().dt).0);
+    }
+}
+
+#[test]
+fn test_fixed_body_anchors_orbit() {
+    // A fixed unit mass holds a circular orbit without recoil, even with a velocity set
+    let bodies = vec![
+        Body::new([0.0, 0.0], [1.0, 0.0], 1.0).with_motion(Motion::Fixed),
+        Body::new([1.0, 0.0], [0.0, 1.0], 0.1),
+    ];
+    let params = SimulationParams { dt: 0.001, epsilon: 0.0, ..SimulationParams::default() };
+    let mut sim = CpuSingleThreaded::new(bodies.clone(), params);
+
+    sim.step(6283);
+    let result = sim.get_bodies();
+    assert_eq!(result[0], bodies[0]);
+    assert_relative_eq!(result[1].position[0], 1.0, epsilon = 2e-2);
+    assert_relative_eq!(result[1].position[1], 0.0, epsilon = 2e-2);
+    assert_relative_eq!(sim.time(), 6283.0 * params.dt, max_relative = 1e-4);
+}
+
+#[test]
+fn test_kinematic_body_drifts_but_pulls() {
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.5, -0.25], 10.0).with_motion(Motion::Kinematic),
+        Body::new([1.0, 0.0], [0.0, 0.0], 1.0),
+    ];
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let mut sim = CpuSingleThreaded::new(bodies, params);
+
+    sim.step(10);
+    let result = sim.get_bodies();
+    assert_eq!(result[0].velocity, [0.5, -0.25]);
+    assert_relative_eq!(result[0].position[0], 0.05, max_relative = 1e-5);
+    assert_relative_eq!(result[0].position[1], -0.025, max_relative = 1e-5);
+    assert_eq!(result[0].motion(), Motion::Kinematic);
+    assert!(result[1].velocity[0] < 0.0);
+}
+
+#[test]
+fn test_trajectory_drives_body() {
+    let params = SimulationParams { dt: 0.01, ..SimulationParams::default() };
+    let mut sim = CpuMultiThreaded::new(pinned_system(), params);
+    sim.set_trajectory(2, perturber());
+    assert_eq!(sim.get_bodies()[2].position, [2.0, 0.0]);
+    assert_eq!(sim.get_bodies()[2].motion(), Motion::Kinematic);
+
+    sim.step(50);
+    let (position, velocity) = perturber()(sim.time());
+    let driven = sim.get_bodies()[2];
+    assert_eq!(driven.position, position);
+    assert_eq!(driven.velocity, velocity);
+    assert_relative_eq!(sim.time(), 0.5, max_relative = 1e-5);
+}
+
+#[test]
+fn test_kinematics_backends_agree() {
+    let bodies = pinned_system();
+    let params = SimulationParams::default();
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.set_trajectory(2, perturber());
+    reference.step(10);
+    let expected = reference.get_bodies();
+    assert_eq!(expected[0], bodies[0]);
+
+    let mut rayon = CpuMultiThreaded::new(bodies.clone(), params);
+    rayon.set_trajectory(2, perturber());
+    let mut simd = SimdSingleThreaded::new(bodies.clone(), params);
+    simd.set_trajectory(2, perturber());
+    let mut simd_rayon = SimdMultiThreaded::new(bodies.clone(), params);
+    simd_rayon.set_trajectory(2, perturber());
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies);
+    aligned.set_params(params);
+    aligned.set_trajectory(2, perturber());
+
+    let mut sims: Vec<Box<dyn Simulation>> =
+        vec![Box::new(rayon), Box::new(simd), Box::new(simd_rayon), Box::new(aligned)];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        let result = sim.get_bodies();
+        compare_bodies(&expected, &result, 1e-3);
+        assert_eq!(result[0], expected[0]);
+        assert_eq!(result[1].motion(), Motion::Kinematic);
+    }
+}
+
+#[tokio::test]
+async fn test_kinematics_gpu_matches_cpu() {
+    let bodies = pinned_system();
+    let params = SimulationParams::default();
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    cpu.set_trajectory(2, perturber());
+    let mut gpu = GpuSimulator::new(bodies.clone(), params).await;
+    gpu.set_trajectory(2, perturber());
+
+    cpu.step(10);
+    gpu.step(10);
+    let result = gpu.get_bodies();
+    compare_bodies(&cpu.get_bodies(), &result, 1e-3);
+    assert_eq!(result[0], bodies[0]);
+    assert_eq!(result[2].motion(), Motion::Kinematic);
+    assert_relative_eq!(gpu.time(), cpu.time());
+
+    gpu.set_bodies(bodies[..2].to_vec());
+    gpu.step(2);
+    assert_eq!(gpu.get_bodies().len(), 2);
+}
+
+#[tokio::test]
+async fn test_gpu_trajectory_after_steps_keeps_other_bodies() {
+    let bodies = pinned_system();
+    let mut gpu = GpuSimulator::new(bodies.clone(), SimulationParams::default()).await;
+    gpu.step(10);
+    let before = gpu.get_bodies();
+
+    gpu.set_trajectory(5, perturber());
+    let after = gpu.get_bodies();
+    for i in (0..bodies.len()).filter(|&i| i != 5) {
+        assert_eq!(after[i], before[i]);
+    }
+    assert_eq!(after[5].mass, bodies[5].mass);
+    assert_eq!(after[5].motion(), Motion::Kinematic);
+    assert_eq!(after[5].position, perturber()(gpu.time()).0);
+}
+
+#[tokio::test]
+async fn test_unknown_flag_is_free_on_gpu_and_cpu() {
+    let mut bodies = pinned_system();
+    bodies[3].padding0 = 3u32.to_ne_bytes();
+    let params = SimulationParams::default();
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    let mut gpu = GpuSimulator::new(bodies.clone(), params).await;
+    cpu.step(10);
+    gpu.step(10);
+
+    let expected = cpu.get_bodies();
+    let result = gpu.get_bodies();
+    compare_bodies(&expected, &result, 1e-3);
+    assert_ne!(result[3].position, bodies[3].position);
+    assert_eq!(expected[3].padding0, bodies[3].padding0);
+    assert_eq!(result[3].padding0, bodies[3].padding0);
+    assert_eq!(result[3].motion(), Motion::Free);
+}
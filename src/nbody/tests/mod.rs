@@ -1,5 +1,18 @@
 // Test modules
 mod cpu_single_tests;
+mod dissipation_tests;
 mod comparison_tests;
+mod cosmology_tests;
+mod escape_tests;
+mod external_field_tests;
 mod integration_tests;
+mod io_tests;
+mod kinematics_tests;
+mod periodic_tests;
+mod post_newtonian_tests;
+mod recorder_tests;
+mod rotating_frame_tests;
+mod species_tests;
+mod test_particle_tests;
+mod utils_tests;
 
@@ -0,0 +1,132 @@
+// Periodic box tests - minimum image, wrapping and Ewald summation in every backend
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::tests::integration_tests::compare_bodies;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+
+const SEED: u64 = 42;
+
+fn periodic_params(box_size: f32, ewald: u32) -> SimulationParams {
+    SimulationParams {
+        dt: 0.001,
+        epsilon: 1e-4,
+        g_constant: 1.0,
+        box_size,
+        ewald,
+        ..SimulationParams::default()
+    }
+}
+
+/// Random bodies in the box `[-1, 1)²` with small velocities.
+fn periodic_bodies(n: usize) -> Vec<Body> {
+    utils::generate_random_bodies_seeded(n, 0.01, SEED)
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| Body::new(b.position.map(|x| x.min(0.999)), [0.1 * (i % 3) as f32 - 0.1, 0.05], b.mass))
+        .collect()
+}
+
+// ========== Minimum image and wrapping ==========
+
+#[test]
+fn test_minimum_image_attracts_across_boundary() {
+    let bodies = vec![
+        Body::new([-0.45, 0.0], [0.0, 0.0], 1.0),
+        Body::new([0.45, 0.0], [0.0, 0.0], 1.0),
+    ];
+
+    let mut open = CpuSingleThreaded::new(bodies.clone(), periodic_params(0.0, 0));
+    let mut boxed = CpuSingleThreaded::new(bodies, periodic_params(1.0, 0));
+    open.step(1);
+    boxed.step(1);
+
+    // Open: pulled towards each other through the middle, periodic: through the boundary
+    assert!(open.get_bodies()[0].velocity[0] > 0.0);
+    let result = boxed.get_bodies();
+    assert!(result[0].velocity[0] < 0.0);
+    assert_relative_eq!(result[0].velocity[0], -0.001 / 0.01, max_relative = 1e-3);
+}
+
+#[test]
+fn test_positions_wrap_into_box() {
+    let bodies = vec![Body::new([0.49, -0.49], [10.0, -10.0], 1.0)];
+    let mut sim = CpuSingleThreaded::new(bodies, periodic_params(1.0, 0));
+    sim.step(2);
+
+    let result = sim.get_bodies();
+    assert_relative_eq!(result[0].position[0], -0.49, epsilon = 1e-5);
+    assert_relative_eq!(result[0].position[1], 0.49, epsilon = 1e-5);
+}
+
+// ========== Ewald summation ==========
+
+#[test]
+fn test_ewald_acceleration() {
+    // Reference from a direct image sum over a disk of radius 150 L
+    let a = periodic::ewald_acceleration([0.3, 0.2], 1.0, 0.0);
+    assert_relative_eq!(a[0], 5.00361, max_relative = 1e-4);
+    assert_relative_eq!(a[1], 3.63854, max_relative = 1e-4);
+
+    // Half a box away every image has a mirror partner
+    let a = periodic::ewald_acceleration([0.5, 0.0], 1.0, 0.0);
+    assert!(a[0].abs() < 1e-4 && a[1].abs() < 1e-4, "{:?}", a);
+
+    // Close pairs are Newtonian, and the result scales with 1 / L²
+    let a = periodic::ewald_acceleration([0.01, 0.0], 1.0, 0.0);
+    assert_relative_eq!(a[0], 1e4, max_relative = 1e-3);
+    let scaled = periodic::ewald_acceleration([0.6, 0.4], 2.0, 0.0);
+    assert_relative_eq!(scaled[0], 5.00361 / 4.0, max_relative = 1e-4);
+
+    // Periodic in the separation
+    let shifted = periodic::ewald_acceleration([1.3, -0.8], 1.0, 0.0);
+    assert_relative_eq!(shifted[0], 5.00361, max_relative = 1e-4);
+    assert_relative_eq!(shifted[1], 3.63854, max_relative = 1e-4);
+}
+
+// ========== Backend agreement ==========
+
+fn cpu_backends(bodies: &[Body], params: SimulationParams, steps: usize) -> Vec<Vec<Body>> {
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.to_vec());
+    aligned.set_params(params);
+    let mut sims: Vec<Box<dyn Simulation>> = vec![
+        Box::new(CpuMultiThreaded::new(bodies.to_vec(), params)),
+        Box::new(SimdSingleThreaded::new(bodies.to_vec(), params)),
+        Box::new(SimdMultiThreaded::new(bodies.to_vec(), params)),
+        Box::new(aligned),
+    ];
+    sims.iter_mut()
+        .map(|sim| {
+            sim.step(steps);
+            sim.get_bodies()
+        })
+        .collect()
+}
+
+#[test]
+fn test_periodic_backends_agree() {
+    let bodies = periodic_bodies(37);
+    for ewald in [0, 1] {
+        let params = periodic_params(2.0, ewald);
+        let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+        reference.step(20);
+        let expected = reference.get_bodies();
+        assert!(expected.iter().all(|b| b.position.iter().all(|x| (-1.0..1.0).contains(x))));
+
+        for result in cpu_backends(&bodies, params, 20) {
+            compare_bodies(&expected, &result, 1e-3);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_periodic_gpu_matches_cpu() {
+    let bodies = periodic_bodies(37);
+    for ewald in [0, 1] {
+        let params = periodic_params(2.0, ewald);
+        let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+        let mut gpu = GpuSimulator::new(bodies.clone(), params).await;
+        cpu.step(20);
+        gpu.step(20);
+        compare_bodies(&cpu.get_bodies(), &gpu.get_bodies(), 1e-3);
+    }
+}
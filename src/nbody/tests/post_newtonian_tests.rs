@@ -0,0 +1,137 @@
+// Post-Newtonian tests - perihelion precession, test particle limit and backend agreement
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::tests::integration_tests::compare_bodies;
+use std::f64::consts::PI;
+
+/// Unit mass at rest with a test particle at pericentre of `a = 1`, `e = 0.5`.
+fn eccentric_orbit() -> Vec<Body> {
+    let (position, velocity) = utils::OrbitalElements {
+        semi_major_axis: 1.0,
+        eccentricity: 0.5,
+        ..Default::default()
+    }
+    .to_state(1.0);
+    vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0),
+        Body::new([position[0] as f32, position[1] as f32], [velocity[0] as f32, velocity[1] as f32], 0.0),
+    ]
+}
+
+/// Polar angle of the test particle at each pericentre passage.
+fn pericentre_angles(params: SimulationParams, steps: usize) -> Vec<f64> {
+    let mut sim = CpuSingleThreaded::new(eccentric_orbit(), params);
+    let mut track = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        sim.step(1);
+        let p = sim.get_bodies()[1].position;
+        track.push((p[0].hypot(p[1]), (p[1] as f64).atan2(p[0] as f64)));
+    }
+    track
+        .windows(3)
+        .filter(|w| w[1].0 < w[0].0 && w[1].0 < w[2].0)
+        .map(|w| w[1].1)
+        .collect()
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let params = SimulationParams::default();
+    assert!(!post_newtonian::is_enabled(&params));
+
+    let bodies = utils::generate_random_bodies_seeded(10, 0.01, 5);
+    let massive: Vec<usize> = (0..bodies.len()).collect();
+    let relativistic = SimulationParams { speed_of_light: 1e4, ..params };
+    let corrections = post_newtonian::corrections(&bodies, &massive, &relativistic);
+    assert!(corrections.iter().all(|c| c[0].is_finite() && c[1].is_finite()));
+}
+
+#[test]
+fn test_test_particle_limit() {
+    // Around a static mass the correction is GM/(c² r²) [(4GM/r - v²) r̂ + 4 (r̂·v) v]
+    let c = 10.0f32;
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 2.0),
+        Body::new([3.0, 4.0], [0.5, -0.2], 0.0),
+    ];
+    let params = SimulationParams { speed_of_light: c, epsilon: 0.0, ..SimulationParams::default() };
+    let correction = post_newtonian::corrections(&bodies, &[0], &params)[1];
+
+    let (r, r_hat, v) = (5.0f32, [0.6f32, 0.8f32], [0.5f32, -0.2f32]);
+    let v2 = v[0] * v[0] + v[1] * v[1];
+    let r_dot_v = r_hat[0] * v[0] + r_hat[1] * v[1];
+    let factor = 2.0 / (c * c * r * r);
+    for k in 0..2 {
+        let expected = factor * ((4.0 * 2.0 / r - v2) * r_hat[k] + 4.0 * r_dot_v * v[k]);
+        assert_relative_eq!(correction[k], expected, max_relative = 1e-4);
+    }
+}
+
+#[test]
+fn test_perihelion_precession() {
+    // Δω = 6π GM / (c² a (1 - e²)) per orbit, 0.063 rad for c = 20
+    let c = 20.0;
+    let dt = 1e-3;
+    let steps = (10.5 * 2.0 * PI / dt).round() as usize;
+    let newtonian = SimulationParams { dt: dt as f32, epsilon: 0.0, ..SimulationParams::default() };
+    let relativistic = SimulationParams { speed_of_light: c as f32, ..newtonian };
+
+    // The 1PN radial period is longer, so compare the n-th pericentres rather than equal
+    // times; subtracting the Newtonian run removes the integrator's own precession
+    let shifted = pericentre_angles(relativistic, steps);
+    let reference = pericentre_angles(newtonian, steps);
+    let n = shifted.len();
+    assert!(n >= 8);
+    let shift = (shifted[n - 1] - reference[n - 1]).rem_euclid(2.0 * PI);
+    let expected = n as f64 * 6.0 * PI / (c * c * 0.75);
+    assert_relative_eq!(shift, expected, max_relative = 0.05);
+}
+
+#[test]
+fn test_post_newtonian_backends_agree() {
+    let bodies = utils::generate_random_bodies_seeded(20, 0.01, 11);
+    let params = SimulationParams { speed_of_light: 50.0, ..SimulationParams::default() };
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.step(10);
+    let expected = reference.get_bodies();
+
+    let mut plain = CpuSingleThreaded::new(bodies.clone(), SimulationParams::default());
+    plain.step(10);
+    assert_ne!(plain.get_bodies(), expected);
+
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.clone());
+    aligned.set_params(params);
+    let mut sims: Vec<Box<dyn Simulation>> = vec![
+        Box::new(CpuMultiThreaded::new(bodies.clone(), params)),
+        Box::new(SimdSingleThreaded::new(bodies.clone(), params)),
+        Box::new(SimdMultiThreaded::new(bodies, params)),
+        Box::new(aligned),
+    ];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        compare_bodies(&expected, &sim.get_bodies(), 1e-3);
+    }
+}
+
+#[tokio::test]
+#[should_panic(expected = "not available on the GPU")]
+async fn test_gpu_rejects_post_newtonian() {
+    let params = SimulationParams { speed_of_light: 50.0, ..SimulationParams::default() };
+    GpuSimulator::new(eccentric_orbit(), params).await;
+}
+
+#[test]
+#[should_panic(expected = "1PN corrections need open boundaries")]
+fn test_periodic_post_newtonian_rejected_up_front() {
+    let mut sim = CpuSingleThreaded::new(eccentric_orbit(), SimulationParams::default());
+    sim.set_params(SimulationParams { speed_of_light: 50.0, box_size: 10.0, ..SimulationParams::default() });
+}
+
+#[test]
+#[should_panic(expected = "1PN corrections need open boundaries")]
+fn test_aligned_rejects_periodic_post_newtonian() {
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(eccentric_orbit());
+    aligned.set_params(SimulationParams { speed_of_light: 50.0, box_size: 10.0, ..SimulationParams::default() });
+}
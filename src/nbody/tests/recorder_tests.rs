@@ -0,0 +1,136 @@
+// Trajectory recorder tests - sampling points, ring buffer, id selection and streaming
+use crate::nbody::*;
+use crate::nbody::recorder::Sample;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::tests::integration_tests::compare_bodies;
+
+fn three_bodies() -> Vec<Body> {
+    vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0),
+        Body::new([1.0, 0.0], [0.0, 0.5], 1.0),
+        Body::new([0.0, 1.5], [-0.5, 0.0], 1.0),
+    ]
+}
+
+#[test]
+fn test_recorder_samples_every_k_steps() {
+    let params = SimulationParams::default();
+    let mut recorder = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(three_bodies(), params),
+        3,
+        RecorderSink::RingBuffer { capacity: 10 },
+    );
+
+    recorder.step(4);
+    recorder.step(6);
+
+    let steps: Vec<u64> = recorder.samples().map(|s| s.step).collect();
+    assert_eq!(steps, vec![3, 6, 9]);
+    assert_eq!(recorder.steps_taken(), 10);
+
+    // Sample at step 9 must match a plain simulation stepped 9 times
+    let mut reference = CpuSingleThreaded::new(three_bodies(), params);
+    reference.step(9);
+    let last: &Sample = recorder.samples().last().unwrap();
+    let recorded: Vec<Body> = last.bodies.iter().map(|(_, b)| *b).collect();
+    compare_bodies(&recorded, &reference.get_bodies(), 1e-6);
+    assert!((last.time - 9.0 * params.dt).abs() < 1e-6);
+}
+
+#[test]
+fn test_recorder_ring_buffer_and_id_selection() {
+    let mut recorder = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(three_bodies(), SimulationParams::default()),
+        2,
+        RecorderSink::RingBuffer { capacity: 2 },
+    )
+    .with_ids(vec![2, 0]);
+
+    recorder.record().unwrap();
+    recorder.step(10);
+
+    let samples: Vec<&Sample> = recorder.samples().collect();
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].step, 8);
+    assert_eq!(samples[1].step, 10);
+    let ids: Vec<usize> = samples[1].bodies.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![2, 0]);
+}
+
+#[test]
+fn test_recorder_streams_csv() {
+    let path = std::env::temp_dir().join(format!("nbody_recorder_{}.csv", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+
+    let mut recorder = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(three_bodies(), SimulationParams::default()),
+        5,
+        RecorderSink::Stream(Box::new(file)),
+    )
+    .with_ids(vec![1]);
+    recorder.step(10);
+    assert_eq!(recorder.samples().count(), 0);
+    drop(recorder);
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "step,time,id,x,y,vx,vy,mass");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("5,"));
+    assert!(lines[2].starts_with("10,"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_recorder_gpu_matches_cpu() {
+    let params = SimulationParams::default();
+    let mut gpu = TrajectoryRecorder::new(
+        GpuSimulator::new(three_bodies(), params).await,
+        4,
+        RecorderSink::RingBuffer { capacity: 4 },
+    );
+    let mut cpu = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(three_bodies(), params),
+        4,
+        RecorderSink::RingBuffer { capacity: 4 },
+    );
+
+    gpu.step(8);
+    cpu.step(8);
+
+    for (g, c) in gpu.samples().zip(cpu.samples()) {
+        assert_eq!(g.step, c.step);
+        let g_bodies: Vec<Body> = g.bodies.iter().map(|(_, b)| *b).collect();
+        let c_bodies: Vec<Body> = c.bodies.iter().map(|(_, b)| *b).collect();
+        compare_bodies(&g_bodies, &c_bodies, 0.01);
+    }
+    assert_eq!(gpu.samples().count(), 2);
+}
+
+struct FailingWriter;
+
+impl std::io::Write for FailingWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_recorder_keeps_write_error_for_finish() {
+    let mut recorder = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(three_bodies(), SimulationParams::default()),
+        2,
+        RecorderSink::Stream(Box::new(FailingWriter)),
+    );
+
+    recorder.step(6);
+    assert_eq!(recorder.steps_taken(), 6);
+    let err = recorder.finish().unwrap_err();
+    assert_eq!(err.to_string(), "disk full");
+    assert!(recorder.finish().is_ok());
+}
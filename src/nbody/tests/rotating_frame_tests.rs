@@ -0,0 +1,138 @@
+// Rotating frame tests - frame conversions, co-rotating orbits and the restricted three-body problem
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::rotating_frame::{jacobi_integral, to_inertial, to_rotating};
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::tests::integration_tests::compare_bodies;
+
+const MU: f32 = 0.01;
+
+/// Primaries of mass 1 - μ and μ one unit apart, pinned in the frame co-rotating with them.
+fn restricted_three_body(tracer: Body) -> Vec<Body> {
+    vec![
+        Body::new([-MU, 0.0], [0.0, 0.0], 1.0 - MU).with_motion(Motion::Fixed),
+        Body::new([1.0 - MU, 0.0], [0.0, 0.0], MU).with_motion(Motion::Fixed),
+        tracer,
+    ]
+}
+
+fn corotating(dt: f32) -> SimulationParams {
+    SimulationParams { dt, epsilon: 0.0, omega: 1.0, ..SimulationParams::default() }
+}
+
+fn primaries_potential(position: [f32; 2]) -> f32 {
+    let r1 = (position[0] + MU).hypot(position[1]);
+    let r2 = (position[0] - 1.0 + MU).hypot(position[1]);
+    -(1.0 - MU) / r1 - MU / r2
+}
+
+#[test]
+fn test_frame_round_trip() {
+    let bodies = utils::generate_random_bodies_seeded(10, 0.01, 4);
+    let back = to_rotating(&to_inertial(&bodies, 0.7, 2.5), 0.7, 2.5);
+    compare_bodies(&bodies, &back, 1e-5);
+
+    // A body at rest in the frame moves on a circle in the inertial frame
+    let resting = vec![Body::new([2.0, 0.0], [0.0, 0.0], 1.0)];
+    let inertial = to_inertial(&resting, 0.5, std::f32::consts::PI);
+    assert_relative_eq!(inertial[0].position[0], 0.0, epsilon = 1e-6);
+    assert_relative_eq!(inertial[0].position[1], 2.0, epsilon = 1e-6);
+    assert_relative_eq!(inertial[0].velocity[0], -1.0, epsilon = 1e-6);
+    assert_relative_eq!(inertial[0].velocity[1], 0.0, epsilon = 1e-6);
+}
+
+#[test]
+fn test_circular_orbit_is_stationary_when_corotating() {
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0).with_motion(Motion::Fixed),
+        Body::new([1.0, 0.0], [0.0, 0.0], 0.0),
+    ];
+    let mut sim = CpuSingleThreaded::new(bodies, corotating(0.01));
+
+    sim.step(1000);
+    let tracer = sim.get_bodies()[1];
+    assert_relative_eq!(tracer.position[0], 1.0, epsilon = 1e-4);
+    assert_relative_eq!(tracer.position[1], 0.0, epsilon = 1e-4);
+}
+
+#[test]
+fn test_l4_is_stable() {
+    let l4 = [0.5 - MU, 3.0f32.sqrt() / 2.0];
+    let tracer = Body::new([l4[0] + 0.01, l4[1]], [0.0, 0.0], 0.0);
+    let mut sim = CpuSingleThreaded::new(restricted_three_body(tracer), corotating(0.005));
+
+    // Ten orbits of the primaries; the tracer librates on a tadpole orbit around L4
+    for _ in 0..126 {
+        sim.step(100);
+        let tracer = sim.get_bodies()[2];
+        assert!((tracer.position[0] - l4[0]).hypot(tracer.position[1] - l4[1]) < 0.25);
+    }
+}
+
+#[test]
+fn test_jacobi_integral_is_conserved() {
+    let tracer = Body::new([0.0, 1.5], [0.3, 0.0], 0.0);
+    let mut sim = CpuSingleThreaded::new(restricted_three_body(tracer), corotating(0.001));
+    let start = jacobi_integral(&tracer, primaries_potential(tracer.position), 1.0);
+
+    for _ in 0..10 {
+        sim.step(500);
+        let tracer = sim.get_bodies()[2];
+        let jacobi = jacobi_integral(&tracer, primaries_potential(tracer.position), 1.0);
+        assert_relative_eq!(jacobi, start, max_relative = 5e-3);
+    }
+}
+
+#[test]
+fn test_rotating_run_matches_inertial_run() {
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, -0.05], 1.0),
+        Body::new([1.0, 0.0], [0.0, 0.9], 0.05),
+        Body::new([-1.5, 0.3], [0.1, -0.7], 0.01),
+    ];
+    let params = SimulationParams { dt: 1e-4, epsilon: 0.0, ..SimulationParams::default() };
+    let omega = 0.8;
+
+    let mut inertial = CpuSingleThreaded::new(bodies.clone(), params);
+    let mut rotating = CpuSingleThreaded::new(to_rotating(&bodies, omega, 0.0), SimulationParams { omega, ..params });
+    inertial.step(10000);
+    rotating.step(10000);
+
+    compare_bodies(&inertial.get_bodies(), &to_inertial(&rotating.get_bodies(), omega, rotating.time()), 1e-3);
+}
+
+#[test]
+fn test_rotating_frame_backends_agree() {
+    let bodies = utils::generate_random_bodies_seeded(30, 0.01, 8);
+    let params = SimulationParams { omega: 0.5, ..SimulationParams::default() };
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.step(10);
+    let expected = reference.get_bodies();
+
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.clone());
+    aligned.set_params(params);
+    let mut sims: Vec<Box<dyn Simulation>> = vec![
+        Box::new(CpuMultiThreaded::new(bodies.clone(), params)),
+        Box::new(SimdSingleThreaded::new(bodies.clone(), params)),
+        Box::new(SimdMultiThreaded::new(bodies, params)),
+        Box::new(aligned),
+    ];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        compare_bodies(&expected, &sim.get_bodies(), 1e-3);
+    }
+}
+
+#[tokio::test]
+async fn test_rotating_frame_gpu_matches_cpu() {
+    let l4 = [0.5 - MU, 3.0f32.sqrt() / 2.0];
+    let bodies = restricted_three_body(Body::new([l4[0] + 0.01, l4[1]], [0.0, 0.0], 0.0));
+    let params = corotating(0.005);
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    let mut gpu = GpuSimulator::new(bodies, params).await;
+    cpu.step(200);
+    gpu.step(200);
+    compare_bodies(&cpu.get_bodies(), &gpu.get_bodies(), 1e-3);
+}
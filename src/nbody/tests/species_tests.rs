@@ -0,0 +1,178 @@
+// Species tests - type flags, coupling matrix, per-type softening and output selection
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use crate::nbody::species::{select, DARK_MATTER, GAS, STAR, TRACER};
+use crate::nbody::tests::integration_tests::compare_bodies;
+
+/// Stars and dark matter interleaved, with a few gas tracers.
+fn mixed_species() -> Vec<Body> {
+    utils::generate_random_bodies_seeded(40, 0.01, 21)
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| body.with_species([STAR, DARK_MATTER, STAR, DARK_MATTER, GAS][i % 5]))
+        .collect()
+}
+
+fn halo_table() -> SpeciesTable {
+    SpeciesTable::uniform(1e-6).softening(DARK_MATTER, 1e-3).ignores(STAR, DARK_MATTER).coupling(GAS, STAR, 0.5)
+}
+
+#[test]
+fn test_species_flag_round_trip() {
+    let body = Body::new([1.0, 2.0], [3.0, 4.0], 5.0);
+    assert_eq!(body.species(), STAR);
+    let flagged = body.with_species(TRACER).with_motion(Motion::Fixed);
+    assert_eq!(flagged.species(), TRACER);
+    assert_eq!(flagged.motion(), Motion::Fixed);
+    assert_eq!(flagged.with_motion(Motion::Free).species(), TRACER);
+
+    let selected = select(&mixed_species(), &[GAS]);
+    assert_eq!(selected.len(), 8);
+    assert!(selected.iter().all(|(i, body)| i % 5 == 4 && body.species() == GAS));
+}
+
+#[test]
+#[should_panic(expected = "out of range")]
+fn test_species_out_of_range() {
+    Body::new([0.0, 0.0], [0.0, 0.0], 1.0).with_species(4);
+}
+
+#[test]
+fn test_uniform_table_changes_nothing() {
+    let params = SimulationParams::default();
+    let mut plain = CpuSingleThreaded::new(mixed_species(), params);
+    let mut uniform = CpuSingleThreaded::new(mixed_species(), params);
+    uniform.set_species_table(Some(SpeciesTable::uniform(params.epsilon)));
+
+    plain.step(10);
+    uniform.step(10);
+    assert_eq!(plain.get_bodies(), uniform.get_bodies());
+}
+
+#[test]
+fn test_stars_ignore_dark_matter() {
+    let bodies: Vec<Body> = mixed_species().into_iter().filter(|b| b.species() != GAS).collect();
+    let params = SimulationParams::default();
+    let table = SpeciesTable::uniform(params.epsilon).ignores(STAR, DARK_MATTER);
+
+    let mut with_halo = CpuSingleThreaded::new(bodies.clone(), params);
+    with_halo.set_species_table(Some(table));
+    let stars: Vec<Body> = bodies.iter().copied().filter(|b| b.species() == STAR).collect();
+    let mut alone = CpuSingleThreaded::new(stars, params);
+    alone.set_species_table(Some(table));
+
+    with_halo.step(10);
+    alone.step(10);
+    let result = with_halo.get_bodies();
+    let stars: Vec<Body> = select(&result, &[STAR]).into_iter().map(|(_, b)| b).collect();
+    assert_eq!(stars, alone.get_bodies());
+
+    // Dark matter still falls towards the stars
+    let moved = result.iter().zip(&bodies).filter(|(b, _)| b.species() == DARK_MATTER);
+    assert!(moved.into_iter().all(|(after, before)| after.velocity != before.velocity));
+}
+
+#[test]
+fn test_per_species_softening() {
+    let pair = |species: u32| {
+        vec![
+            Body::new([0.0, 0.0], [0.0, 0.0], 1.0).with_species(species),
+            Body::new([0.01, 0.0], [0.0, 0.0], 1.0),
+        ]
+    };
+    let params = SimulationParams { dt: 0.001, epsilon: 0.0, ..SimulationParams::default() };
+    let table = SpeciesTable::uniform(0.0).softening(DARK_MATTER, 1.0);
+
+    let mut stars = CpuSingleThreaded::new(pair(STAR), params);
+    stars.set_species_table(Some(table));
+    let mut softened = CpuSingleThreaded::new(pair(DARK_MATTER), params);
+    softened.set_species_table(Some(table));
+    stars.step(1);
+    softened.step(1);
+
+    // r² = 1e-4 unsoftened, floored at 1 when either body is dark matter, so a = m r / 1
+    assert_relative_eq!(stars.get_bodies()[0].velocity[0], 1e4 * params.dt, max_relative = 1e-3);
+    assert_relative_eq!(softened.get_bodies()[0].velocity[0], 0.01 * params.dt, max_relative = 1e-3);
+    assert_relative_eq!(softened.get_bodies()[1].velocity[0], -0.01 * params.dt, max_relative = 1e-3);
+}
+
+#[test]
+fn test_species_backends_agree() {
+    let bodies = mixed_species();
+    let params = SimulationParams::default();
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.set_species_table(Some(halo_table()));
+    reference.step(10);
+    let expected = reference.get_bodies();
+
+    let mut rayon = CpuMultiThreaded::new(bodies.clone(), params);
+    rayon.set_species_table(Some(halo_table()));
+    let mut simd = SimdSingleThreaded::new(bodies.clone(), params);
+    simd.set_species_table(Some(halo_table()));
+    let mut simd_rayon = SimdMultiThreaded::new(bodies.clone(), params);
+    simd_rayon.set_species_table(Some(halo_table()));
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies);
+    aligned.set_params(params);
+    aligned.set_species_table(Some(halo_table()));
+
+    let mut sims: Vec<Box<dyn Simulation>> =
+        vec![Box::new(rayon), Box::new(simd), Box::new(simd_rayon), Box::new(aligned)];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        let result = sim.get_bodies();
+        compare_bodies(&expected, &result, 1e-3);
+        assert!(result.iter().zip(&expected).all(|(a, b)| a.species() == b.species()));
+    }
+}
+
+#[test]
+fn test_recorder_selects_species() {
+    let mut recorder = TrajectoryRecorder::new(
+        CpuSingleThreaded::new(mixed_species(), SimulationParams::default()),
+        5,
+        RecorderSink::RingBuffer { capacity: 4 },
+    )
+    .with_species(vec![STAR]);
+
+    recorder.step(5);
+    let sample = recorder.samples().last().unwrap();
+    assert_eq!(sample.bodies.len(), 16);
+    assert!(sample.bodies.iter().all(|(_, body)| body.species() == STAR));
+}
+
+#[tokio::test]
+async fn test_species_gpu_matches_cpu() {
+    let bodies = mixed_species();
+    let params = SimulationParams::default();
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    cpu.set_species_table(Some(halo_table()));
+    let mut gpu = GpuSimulator::new(bodies, params).await;
+    gpu.set_species_table(Some(halo_table()));
+
+    cpu.step(10);
+    gpu.step(10);
+    let result = gpu.get_bodies();
+    compare_bodies(&cpu.get_bodies(), &result, 1e-3);
+    assert_eq!(result[4].species(), GAS);
+}
+
+#[test]
+#[should_panic(expected = "Body 1 has species 9, only 4 exist")]
+fn test_out_of_range_species_rejected_on_load() {
+    let mut stray = Body::new([1.0, 0.0], [0.0, 0.0], 1.0);
+    stray.padding1 = 9u32.to_ne_bytes();
+    let mut sim = CpuSingleThreaded::new(vec![Body::new([0.0, 0.0], [0.0, 0.0], 1.0)], SimulationParams::default());
+    sim.set_species_table(Some(SpeciesTable::uniform(1e-6)));
+    sim.set_bodies(vec![Body::new([0.0, 0.0], [0.0, 0.0], 1.0), stray]);
+}
+
+#[test]
+#[should_panic(expected = "has species 9")]
+fn test_aligned_rejects_out_of_range_species() {
+    let mut stray = Body::new([1.0, 0.0], [0.0, 0.0], 1.0);
+    stray.padding1 = 9u32.to_ne_bytes();
+    simd_alligned_core::SimdAlignedNBodyCore::new(vec![stray]);
+}
@@ -0,0 +1,113 @@
+// Test particle tests - massless bodies feel gravity but exert none
+use crate::nbody::*;
+use approx::assert_relative_eq;
+use crate::nbody::tests::integration_tests::compare_bodies;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+
+const SEED: u64 = 7;
+
+/// Massive bodies interleaved with tracers, so SIMD chunks hold both kinds.
+fn mixed_bodies() -> Vec<Body> {
+    utils::generate_random_bodies_seeded(45, 0.01, SEED)
+        .into_iter()
+        .enumerate()
+        .map(|(i, body)| if i % 3 == 0 { body } else { Body { mass: 0.0, ..body } })
+        .collect()
+}
+
+#[test]
+fn test_massive_indices() {
+    let sim = CpuSingleThreaded::new(mixed_bodies(), SimulationParams::default());
+    let bodies = sim.get_bodies();
+    assert_eq!(bodies.iter().filter(|b| b.mass > 0.0).count(), 15);
+
+    let state = SimulationState::new(bodies, SimulationParams::default());
+    assert_eq!(state.massive().len(), 15);
+    assert!(state.massive().iter().all(|&i| i % 3 == 0));
+}
+
+#[test]
+fn test_test_particles_do_not_perturb_massive_bodies() {
+    let bodies = mixed_bodies();
+    let massive: Vec<Body> = bodies.iter().copied().filter(|b| b.mass > 0.0).collect();
+    let params = SimulationParams::default();
+
+    let mut with_tracers = CpuSingleThreaded::new(bodies, params);
+    let mut without = CpuSingleThreaded::new(massive, params);
+    with_tracers.step(20);
+    without.step(20);
+
+    let result: Vec<Body> = with_tracers.get_bodies().into_iter().filter(|b| b.mass > 0.0).collect();
+    assert_eq!(result, without.get_bodies());
+}
+
+#[test]
+fn test_test_particle_circular_orbit() {
+    let bodies = vec![
+        Body::new([0.0, 0.0], [0.0, 0.0], 1.0),
+        Body::new([1.0, 0.0], [0.0, 1.0], 0.0),
+        Body::new([0.0, -2.0], [0.5f32.sqrt(), 0.0], 0.0),
+    ];
+    let params = SimulationParams { dt: 0.001, epsilon: 0.0, ..SimulationParams::default() };
+    let mut sim = CpuSingleThreaded::new(bodies, params);
+
+    sim.step(6283);
+    let result = sim.get_bodies();
+    assert_eq!(result[0].position, [0.0, 0.0]);
+    assert_eq!(result[0].velocity, [0.0, 0.0]);
+    assert_relative_eq!(result[1].position[0], 1.0, epsilon = 2e-2);
+    assert_relative_eq!(result[1].position[1], 0.0, epsilon = 2e-2);
+    for tracer in &result[1..] {
+        assert_eq!(tracer.mass, 0.0);
+        assert!(tracer.position[0].is_finite() && tracer.position[1].is_finite());
+    }
+    assert_relative_eq!(result[2].position[0].hypot(result[2].position[1]), 2.0, epsilon = 2e-2);
+}
+
+#[test]
+fn test_test_particle_backends_agree() {
+    let bodies = mixed_bodies();
+    let params = SimulationParams::default();
+
+    let mut reference = CpuSingleThreaded::new(bodies.clone(), params);
+    reference.step(10);
+    let expected = reference.get_bodies();
+
+    let mut aligned = simd_alligned_core::SimdAlignedNBodyCore::new(bodies.clone());
+    aligned.set_params(params);
+    let mut sims: Vec<Box<dyn Simulation>> = vec![
+        Box::new(CpuMultiThreaded::new(bodies.clone(), params)),
+        Box::new(SimdSingleThreaded::new(bodies.clone(), params)),
+        Box::new(SimdMultiThreaded::new(bodies.clone(), params)),
+        Box::new(aligned),
+    ];
+    for sim in sims.iter_mut() {
+        sim.step(10);
+        compare_bodies(&expected, &sim.get_bodies(), 1e-3);
+    }
+}
+
+#[test]
+fn test_set_bodies_updates_massive_set() {
+    let params = SimulationParams::default();
+    let mut sim = SimdSingleThreaded::new(mixed_bodies(), params);
+    let all_massive = utils::generate_random_bodies_seeded(45, 0.01, SEED);
+    sim.set_bodies(all_massive.clone());
+    sim.step(5);
+
+    let mut reference = CpuSingleThreaded::new(all_massive, params);
+    reference.step(5);
+    compare_bodies(&reference.get_bodies(), &sim.get_bodies(), 1e-3);
+}
+
+#[tokio::test]
+async fn test_test_particles_gpu_matches_cpu() {
+    let bodies = mixed_bodies();
+    let params = SimulationParams::default();
+
+    let mut cpu = CpuSingleThreaded::new(bodies.clone(), params);
+    let mut gpu = GpuSimulator::new(bodies, params).await;
+    cpu.step(10);
+    gpu.step(10);
+    compare_bodies(&cpu.get_bodies(), &gpu.get_bodies(), 1e-3);
+}
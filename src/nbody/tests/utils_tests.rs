@@ -0,0 +1,539 @@
+// Initial-condition generator tests
+use crate::nbody::*;
+use crate::nbody::initial_conditions::Particle3;
+use crate::nbody::initial_conditions::plummer::sample_plummer;
+use crate::nbody::initial_conditions::profiles;
+use crate::nbody::shader_types::nbody::{Body, SimulationParams};
+use approx::assert_relative_eq;
+
+#[test]
+fn test_seeded_generators_are_reproducible() {
+    let a = utils::generate_random_bodies_seeded(100, 1.0, 7);
+    let b = utils::generate_random_bodies_seeded(100, 1.0, 7);
+    let c = utils::generate_random_bodies_seeded(100, 1.0, 8);
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+
+    let mut rng = utils::seeded_rng(7);
+    assert_eq!(utils::generate_random_bodies_with_rng(&mut rng, 100, 1.0), a);
+}
+
+fn energy_3d(particles: &[Particle3]) -> (f64, f64) {
+    let kinetic: f64 = particles
+        .iter()
+        .map(|(_, v, m)| 0.5 * m * (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]))
+        .sum();
+    let mut potential = 0.0;
+    for i in 0..particles.len() {
+        for j in (i + 1)..particles.len() {
+            let (a, b) = (&particles[i].0, &particles[j].0);
+            let r = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+            potential -= particles[i].2 * particles[j].2 / r;
+        }
+    }
+    (kinetic, potential)
+}
+
+#[test]
+fn test_plummer_in_henon_units() {
+    let particles = sample_plummer(&mut utils::seeded_rng(1), 2000);
+
+    let total_mass: f64 = particles.iter().map(|p| p.2).sum();
+    assert!((total_mass - 1.0).abs() < 1e-9);
+
+    let (kinetic, potential) = energy_3d(&particles);
+    assert!((kinetic + potential + 0.25).abs() < 0.02, "E = {}", kinetic + potential);
+    assert!((2.0 * kinetic / -potential - 1.0).abs() < 0.1, "virial ratio {}", 2.0 * kinetic / -potential);
+}
+
+#[test]
+fn test_plummer_projection() {
+    let bodies = utils::generate_plummer_projected(&mut utils::seeded_rng(1), 500);
+
+    assert_eq!(bodies.len(), 500);
+    let total_mass: f32 = bodies.iter().map(|b| b.mass).sum();
+    assert!((total_mass - 1.0).abs() < 1e-4);
+    let com_x: f32 = bodies.iter().map(|b| b.mass * b.position[0]).sum();
+    let com_vy: f32 = bodies.iter().map(|b| b.mass * b.velocity[1]).sum();
+    assert!(com_x.abs() < 1e-4 && com_vy.abs() < 1e-4);
+}
+
+fn virial_ratio(particles: &[Particle3]) -> f64 {
+    let (kinetic, potential) = energy_3d(particles);
+    2.0 * kinetic / -potential
+}
+
+#[test]
+fn test_king_model_in_henon_units() {
+    for w0 in [3.0, 7.0] {
+        let particles = profiles::sample_king(&mut utils::seeded_rng(2), 2000, w0);
+        let (kinetic, potential) = energy_3d(&particles);
+
+        assert!((kinetic + potential + 0.25).abs() < 0.025, "W0 = {}: E = {}", w0, kinetic + potential);
+        assert!((virial_ratio(&particles) - 1.0).abs() < 0.1, "W0 = {}: Q = {}", w0, virial_ratio(&particles));
+    }
+
+    // Concentrations c = log10(r_t / r_0) from King (1966)
+    for (w0, c) in [(3.0, 0.67), (6.0, 1.26), (9.0, 2.13)] {
+        let concentration = profiles::king_concentration(w0);
+        assert!((concentration - c).abs() < 0.03, "W0 = {}: c = {}", w0, concentration);
+    }
+}
+
+#[test]
+fn test_hernquist_equilibrium() {
+    let particles = profiles::sample_hernquist(&mut utils::seeded_rng(3), 2000, 2.0, 0.5);
+
+    let total_mass: f64 = particles.iter().map(|p| p.2).sum();
+    assert!((total_mass - 2.0).abs() < 1e-9);
+
+    // Half the mass lies within (1 + √2) a
+    let half_mass_radius = (1.0 + 2f64.sqrt()) * 0.5;
+    let inside = particles
+        .iter()
+        .filter(|(x, _, _)| (x[0] * x[0] + x[1] * x[1] + x[2] * x[2]).sqrt() < half_mass_radius)
+        .count();
+    assert!((inside as f64 / 2000.0 - 0.5).abs() < 0.05, "fraction inside: {}", inside as f64 / 2000.0);
+    assert!((virial_ratio(&particles) - 1.0).abs() < 0.15, "Q = {}", virial_ratio(&particles));
+}
+
+#[test]
+fn test_nfw_equilibrium() {
+    let particles = profiles::sample_nfw(&mut utils::seeded_rng(4), 2000, 1.0, 0.1, 10.0);
+
+    let total_mass: f64 = particles.iter().map(|p| p.2).sum();
+    assert!(total_mass > 1.15 && total_mass < 1.25, "total mass {}", total_mass);
+    assert!((virial_ratio(&particles) - 1.0).abs() < 0.15, "Q = {}", virial_ratio(&particles));
+
+    let bodies = utils::generate_nfw_projected(&mut utils::seeded_rng(4), 100, 1.0, 0.1, 10.0);
+    assert_eq!(bodies.len(), 100);
+    assert!(bodies.iter().all(|b| b.position[0].is_finite() && b.velocity[1].is_finite()));
+}
+
+// ========== Disk galaxy ==========
+
+/// Mean rotation and radial dispersion of the bodies with radius in `[r_min, r_max)`.
+fn disk_kinematics(bodies: &[Body], r_min: f32, r_max: f32) -> (f32, f32) {
+    let (mut v_phi, mut v_r2, mut count) = (0.0, 0.0, 0.0);
+    for body in bodies {
+        let [x, y] = body.position;
+        let r = (x * x + y * y).sqrt();
+        if r >= r_min && r < r_max {
+            v_phi += (x * body.velocity[1] - y * body.velocity[0]) / r;
+            v_r2 += ((x * body.velocity[0] + y * body.velocity[1]) / r).powi(2);
+            count += 1.0;
+        }
+    }
+    (v_phi / count, (v_r2 / count).sqrt())
+}
+
+#[test]
+fn test_galaxy_components() {
+    let bodies = utils::GalaxyBuilder::new(1000)
+        .disk_mass(1.0)
+        .bulge(200, 0.3, 0.2)
+        .halo(500, 5.0, 2.0, 10.0)
+        .build(&mut utils::seeded_rng(5));
+    assert_eq!(bodies.len(), 1700);
+
+    let disk_mass: f32 = bodies[..1000].iter().map(|b| b.mass).sum();
+    let bulge_mass: f32 = bodies[1000..1200].iter().map(|b| b.mass).sum();
+    assert!((disk_mass - 1.0).abs() < 1e-4);
+    assert!((bulge_mass - 0.3).abs() < 1e-4);
+
+    let momentum = bodies.iter().fold([0.0f32; 2], |p, b| {
+        [p[0] + b.mass * b.velocity[0], p[1] + b.mass * b.velocity[1]]
+    });
+    assert!(momentum[0].abs() < 1e-4 && momentum[1].abs() < 1e-4);
+
+    let angular_momentum: f32 = bodies[..1000]
+        .iter()
+        .map(|b| b.position[0] * b.velocity[1] - b.position[1] * b.velocity[0])
+        .sum();
+    assert!(angular_momentum > 0.0);
+
+    let again = utils::GalaxyBuilder::new(1000)
+        .disk_mass(1.0)
+        .bulge(200, 0.3, 0.2)
+        .halo(500, 5.0, 2.0, 10.0)
+        .build(&mut utils::seeded_rng(5));
+    assert_eq!(bodies, again);
+}
+
+#[test]
+fn test_galaxy_disk_rotation_curve_and_toomre_q() {
+    // A nearly cold bare disk rotates on the Freeman curve, which peaks at 0.62 near 2.2 R_d
+    let cold = utils::GalaxyBuilder::new(20000).toomre_q(0.01).build(&mut utils::seeded_rng(6));
+    let (v_phi, _) = disk_kinematics(&cold, 2.0, 2.4);
+    assert!((v_phi - 0.62).abs() < 0.01, "v_phi = {}", v_phi);
+
+    let q1 = utils::GalaxyBuilder::new(20000).toomre_q(1.0).build(&mut utils::seeded_rng(6));
+    let q2 = utils::GalaxyBuilder::new(20000).toomre_q(2.0).build(&mut utils::seeded_rng(6));
+    let (_, sigma1) = disk_kinematics(&q1, 1.0, 3.0);
+    let (_, sigma2) = disk_kinematics(&q2, 1.0, 3.0);
+    assert!((sigma2 / sigma1 - 2.0).abs() < 0.15, "sigma ratio {}", sigma2 / sigma1);
+}
+
+// ========== Galaxy collision ==========
+
+fn centre_of_mass(bodies: &[Body]) -> ([f32; 2], [f32; 2]) {
+    let total: f32 = bodies.iter().map(|b| b.mass).sum();
+    bodies.iter().fold(([0.0; 2], [0.0; 2]), |(x, v), b| {
+        let w = b.mass / total;
+        (
+            [x[0] + w * b.position[0], x[1] + w * b.position[1]],
+            [v[0] + w * b.velocity[0], v[1] + w * b.velocity[1]],
+        )
+    })
+}
+
+#[test]
+fn test_collision_orbit() {
+    let first = utils::generate_plummer_projected(&mut utils::seeded_rng(9), 300);
+    let second: Vec<Body> = utils::generate_plummer_projected(&mut utils::seeded_rng(10), 200)
+        .into_iter()
+        .map(|b| Body::new(b.position, b.velocity, b.mass * 0.5))
+        .collect();
+
+    for e in [1.0f32, 1.5] {
+        let builder = utils::CollisionBuilder::new(2.0).eccentricity(e as f64).separation(20.0);
+        let bodies = builder.build(&first, &second);
+        assert_eq!(bodies.len(), 500);
+
+        let (x, v) = centre_of_mass(&bodies);
+        assert!(x.iter().chain(v.iter()).all(|c| c.abs() < 1e-4), "{:?} {:?}", x, v);
+
+        let (x1, v1) = centre_of_mass(&bodies[..300]);
+        let (x2, v2) = centre_of_mass(&bodies[300..]);
+        let dx = [x2[0] - x1[0], x2[1] - x1[1]];
+        let dv = [v2[0] - v1[0], v2[1] - v1[1]];
+        let r = (dx[0] * dx[0] + dx[1] * dx[1]).sqrt();
+        assert!((r - 20.0).abs() < 1e-3, "separation {}", r);
+        assert!(dx[0] * dv[0] + dx[1] * dv[1] < 0.0, "galaxies must approach");
+
+        // Vis-viva: v² = GM (2/r + (e - 1)/q) and the angular momentum fixes the pericentre
+        let v2_rel = dv[0] * dv[0] + dv[1] * dv[1];
+        assert!((v2_rel - 1.5 * (2.0 / r + (e - 1.0) / 2.0)).abs() < 1e-3, "v² = {}", v2_rel);
+        let h = dx[0] * dv[1] - dx[1] * dv[0];
+        assert!((h * h - 1.5 * 2.0 * (1.0 + e)).abs() < 1e-2, "h² = {}", h * h);
+    }
+}
+
+#[test]
+fn test_collision_retrograde_inclination() {
+    let disk = utils::GalaxyBuilder::new(500).build(&mut utils::seeded_rng(11));
+    let spin = |bodies: &[Body]| -> f32 {
+        let (x, v) = centre_of_mass(bodies);
+        bodies
+            .iter()
+            .map(|b| {
+                b.mass * ((b.position[0] - x[0]) * (b.velocity[1] - v[1]) - (b.position[1] - x[1]) * (b.velocity[0] - v[0]))
+            })
+            .sum()
+    };
+
+    let bodies = utils::CollisionBuilder::new(3.0)
+        .inclinations(0.0, std::f64::consts::PI)
+        .build(&disk, &disk);
+    assert!((spin(&bodies[..500]) - spin(&disk)).abs() < 1e-3);
+    assert!((spin(&bodies[500..]) + spin(&disk)).abs() < 1e-3);
+}
+
+// ========== Kepler orbits ==========
+
+#[test]
+fn test_orbital_elements_round_trip() {
+    let cases = [
+        utils::OrbitalElements {
+            semi_major_axis: 1.3,
+            eccentricity: 0.4,
+            inclination: 0.3,
+            longitude_of_ascending_node: 1.1,
+            argument_of_periapsis: 2.5,
+            mean_anomaly: 4.0,
+            ..Default::default()
+        },
+        utils::OrbitalElements {
+            semi_major_axis: 0.7,
+            eccentricity: 0.95,
+            inclination: 2.8,
+            longitude_of_ascending_node: 5.0,
+            argument_of_periapsis: 0.2,
+            mean_anomaly: 0.1,
+            ..Default::default()
+        },
+        utils::OrbitalElements {
+            semi_major_axis: -2.0,
+            eccentricity: 1.7,
+            inclination: 1.0,
+            longitude_of_ascending_node: 0.5,
+            argument_of_periapsis: 3.0,
+            mean_anomaly: -1.5,
+            ..Default::default()
+        },
+    ];
+
+    for elements in cases {
+        let (position, velocity) = elements.to_state(3.0);
+        let back = utils::OrbitalElements::from_state(position, velocity, 3.0);
+        for (a, b) in [
+            (elements.semi_major_axis, back.semi_major_axis),
+            (elements.eccentricity, back.eccentricity),
+            (elements.inclination, back.inclination),
+            (elements.longitude_of_ascending_node, back.longitude_of_ascending_node),
+            (elements.argument_of_periapsis, back.argument_of_periapsis),
+            (elements.mean_anomaly, back.mean_anomaly),
+        ] {
+            assert!((a - b).abs() < 1e-9, "{:?} -> {:?}", elements, back);
+        }
+    }
+}
+
+#[test]
+fn test_parabolic_orbit() {
+    let elements = utils::OrbitalElements::parabolic(2.0, -3.0);
+    let (position, velocity) = elements.to_state(1.0);
+    let r = (position[0].powi(2) + position[1].powi(2)).sqrt();
+    let v2 = velocity[0].powi(2) + velocity[1].powi(2);
+    assert!((0.5 * v2 - 1.0 / r).abs() < 1e-12, "energy {}", 0.5 * v2 - 1.0 / r);
+
+    let back = utils::OrbitalElements::from_state([2.0, 0.0, 0.0], [0.0, 1.0, 0.0], 1.0);
+    assert_eq!(back.eccentricity, 1.0);
+    assert_eq!(back.semi_major_axis, f64::INFINITY);
+    assert!((back.pericentre_distance - 2.0).abs() < 1e-12);
+    assert_eq!(back.mean_anomaly, 0.0);
+
+    let tilted = utils::OrbitalElements {
+        inclination: 0.4,
+        longitude_of_ascending_node: 2.0,
+        argument_of_periapsis: 1.0,
+        ..utils::OrbitalElements::parabolic(1.5, 0.8)
+    };
+    let (position, velocity) = tilted.to_state(3.0);
+    let back = utils::OrbitalElements::from_state(position, velocity, 3.0);
+    for (a, b) in [
+        (tilted.pericentre_distance, back.pericentre_distance),
+        (tilted.inclination, back.inclination),
+        (tilted.longitude_of_ascending_node, back.longitude_of_ascending_node),
+        (tilted.argument_of_periapsis, back.argument_of_periapsis),
+        (tilted.mean_anomaly, back.mean_anomaly),
+    ] {
+        assert!((a - b).abs() < 1e-9, "{:?} -> {:?}", tilted, back);
+    }
+}
+
+#[test]
+fn test_circular_binary_is_circular() {
+    let bodies = utils::generate_circular_binary();
+    let elements = utils::OrbitalElements::from_bodies(&bodies[1], &bodies[0], 1.0);
+    assert!(elements.eccentricity < 1e-6, "e = {}", elements.eccentricity);
+    assert!((elements.semi_major_axis - 2.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_solar_system_builder() {
+    let bodies = utils::PlanetarySystemBuilder::solar_system().build();
+    assert_eq!(bodies.len(), 9);
+
+    let (_, v) = centre_of_mass(&bodies);
+    assert!(v[0].abs() < 1e-6 && v[1].abs() < 1e-6);
+
+    let g = 4.0 * std::f64::consts::PI * std::f64::consts::PI;
+    let earth = utils::OrbitalElements::from_bodies(&bodies[3], &bodies[0], g);
+    assert!((earth.semi_major_axis - 1.0).abs() < 1e-4, "a = {}", earth.semi_major_axis);
+    assert!((earth.eccentricity - 0.0167).abs() < 1e-4, "e = {}", earth.eccentricity);
+    assert!((earth.period(g * (bodies[0].mass + bodies[3].mass) as f64) - 1.0).abs() < 1e-3);
+
+    let neptune = utils::OrbitalElements::from_bodies(&bodies[8], &bodies[0], g);
+    assert!((neptune.semi_major_axis - 30.07).abs() < 0.05, "a = {}", neptune.semi_major_axis);
+}
+
+// ========== Initial mass functions ==========
+
+#[test]
+fn test_imf_sampling() {
+    let mut rng = utils::seeded_rng(12);
+
+    // Salpeter between 0.1 and 100 has 95.5% of its stars below one solar mass
+    let salpeter = utils::MassSpectrum::new(utils::InitialMassFunction::Salpeter, 0.1, 100.0);
+    let masses = salpeter.sample_masses(&mut rng, 20000);
+    assert!(masses.iter().all(|&m| (0.1..=100.0).contains(&m)));
+    let below = masses.iter().filter(|&&m| m < 1.0).count() as f64 / 20000.0;
+    assert!((below - 0.955).abs() < 0.01, "fraction below 1: {}", below);
+
+    for (imf, min, max, expected) in [
+        (utils::InitialMassFunction::Kroupa, 0.01, 150.0, 0.384),
+        (utils::InitialMassFunction::Chabrier, 0.01, 100.0, 0.349),
+    ] {
+        let spectrum = utils::MassSpectrum::new(imf, min, max);
+        assert!((spectrum.mean_mass() - expected).abs() < 0.005, "{:?} mean {}", imf, spectrum.mean_mass());
+        let masses = spectrum.sample_masses(&mut rng, 50000);
+        let mean = masses.iter().sum::<f64>() / 50000.0;
+        assert!((mean - expected).abs() < 0.05 * expected, "{:?} sample mean {}", imf, mean);
+    }
+}
+
+#[test]
+fn test_assign_masses_keeps_total() {
+    let mut bodies = utils::generate_plummer_projected(&mut utils::seeded_rng(13), 1000);
+    let positions: Vec<[f32; 2]> = bodies.iter().map(|b| b.position).collect();
+
+    let kroupa = utils::MassSpectrum::new(utils::InitialMassFunction::Kroupa, 0.08, 20.0);
+    utils::assign_masses(&mut utils::seeded_rng(14), &mut bodies, &kroupa);
+
+    let total: f32 = bodies.iter().map(|b| b.mass).sum();
+    assert!((total - 1.0).abs() < 1e-4);
+    assert!(bodies.iter().map(|b| b.position).eq(positions));
+    let (min, max) = bodies.iter().fold((f32::MAX, 0.0f32), |(lo, hi), b| (lo.min(b.mass), hi.max(b.mass)));
+    assert!(max / min > 10.0 && max / min <= 250.0, "mass range {}", max / min);
+}
+
+// ========== Diagnostics and scaling ==========
+
+#[test]
+fn test_two_body_diagnostics() {
+    let bodies = utils::generate_circular_binary();
+    let params = SimulationParams::default();
+
+    assert_relative_eq!(diagnostics::kinetic_energy(&bodies), 2500.0, max_relative = 1e-9);
+    assert_relative_eq!(diagnostics::potential_energy(&bodies, &params), -5000.0, max_relative = 1e-9);
+    assert_relative_eq!(diagnostics::virial_ratio(&bodies, &params), 0.5, max_relative = 1e-9);
+    assert_relative_eq!(diagnostics::angular_momentum(&bodies), -1000.0, max_relative = 1e-9);
+    assert_eq!(diagnostics::linear_momentum(&bodies), [0.0, 0.0]);
+}
+
+#[test]
+fn test_virial_and_henon_scaling() {
+    let params = SimulationParams::default();
+    let mut bodies: Vec<Body> = utils::generate_random_bodies_seeded(300, 2.0, 15)
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| Body::new([b.position[0] + 3.0, b.position[1]], [(i % 7) as f32 - 2.0, (i % 5) as f32], b.mass))
+        .collect();
+
+    utils::to_centre_of_mass_frame(&mut bodies);
+    let (x, v) = diagnostics::centre_of_mass(&bodies);
+    assert!(x.iter().chain(v.iter()).all(|c| c.abs() < 1e-5), "{:?} {:?}", x, v);
+
+    utils::scale_to_virial_ratio(&mut bodies, 0.5, &params);
+    assert_relative_eq!(diagnostics::virial_ratio(&bodies, &params), 0.5, max_relative = 1e-4);
+
+    for body in bodies.iter_mut() {
+        // Same virial ratio, different units
+        body.position = body.position.map(|c| c * 4.0);
+        body.velocity = body.velocity.map(|c| c * 0.5);
+    }
+    utils::scale_to_henon_units(&mut bodies, &params);
+    let henon = SimulationParams { g_constant: 1.0, epsilon: 0.0, ..SimulationParams::default() };
+    assert_relative_eq!(diagnostics::total_mass(&bodies), 1.0, max_relative = 1e-5);
+    assert_relative_eq!(diagnostics::total_energy(&bodies, &henon), -0.25, max_relative = 1e-4);
+    assert_relative_eq!(diagnostics::virial_ratio(&bodies, &henon), 0.5, max_relative = 1e-4);
+}
+
+#[test]
+fn test_henon_scaling_ignores_softening() {
+    let mut bodies: Vec<Body> = utils::generate_random_bodies_seeded(200, 1.0, 4)
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| Body::new(b.position, [(i % 5) as f32 - 2.0, (i % 3) as f32 - 1.0], b.mass))
+        .collect();
+    let softened = SimulationParams { epsilon: 0.5, ..SimulationParams::default() };
+    utils::scale_to_virial_ratio(&mut bodies, 0.5, &SimulationParams { epsilon: 0.0, ..softened });
+
+    utils::scale_to_henon_units(&mut bodies, &softened);
+    let henon = SimulationParams { g_constant: 1.0, epsilon: 0.0, ..SimulationParams::default() };
+    assert_relative_eq!(diagnostics::total_energy(&bodies, &henon), -0.25, max_relative = 1e-4);
+    assert_relative_eq!(diagnostics::virial_ratio(&bodies, &henon), 0.5, max_relative = 1e-4);
+}
+
+// ========== Stress-test setups ==========
+
+#[test]
+fn test_uniform_cold_disk() {
+    let bodies = utils::generate_uniform_disk(&mut utils::seeded_rng(16), 4000, 2.0, 1.0, 0.0);
+    assert_eq!(bodies.len(), 4000);
+    assert!(bodies.iter().all(|b| b.velocity == [0.0, 0.0]));
+
+    let radius = |b: &Body| (b.position[0].powi(2) + b.position[1].powi(2)).sqrt();
+    assert!(bodies.iter().all(|b| radius(b) < 2.05));
+    let inner = bodies.iter().filter(|b| radius(b) < 1.0).count() as f32 / 4000.0;
+    assert!((inner - 0.25).abs() < 0.02, "fraction inside half radius: {}", inner);
+
+    let warm = utils::generate_uniform_disk(&mut utils::seeded_rng(16), 4000, 2.0, 1.0, 0.1);
+    let sigma = (warm.iter().map(|b| b.velocity[0].powi(2)).sum::<f32>() / 4000.0).sqrt();
+    assert!((sigma - 0.1).abs() < 0.005, "sigma = {}", sigma);
+}
+
+#[test]
+fn test_perturbed_lattice() {
+    let cold = utils::generate_perturbed_lattice(&mut utils::seeded_rng(17), 4, 0.5, 1.0, 0.0, 0.0);
+    assert_eq!(cold.len(), 16);
+    assert_eq!(cold[0].position, [-0.75, -0.75]);
+    assert_eq!(cold[15].position, [0.75, 0.75]);
+    assert!(cold.iter().all(|b| b.velocity == [0.0, 0.0]));
+
+    let perturbed = utils::generate_perturbed_lattice(&mut utils::seeded_rng(17), 4, 0.5, 1.0, 0.01, 0.0);
+    let again = utils::generate_perturbed_lattice(&mut utils::seeded_rng(17), 4, 0.5, 1.0, 0.01, 0.0);
+    assert_eq!(perturbed, again);
+    for (a, b) in cold.iter().zip(&perturbed) {
+        let shift = ((a.position[0] - b.position[0]).powi(2) + (a.position[1] - b.position[1]).powi(2)).sqrt();
+        assert!(shift > 0.0 && shift < 0.1);
+    }
+}
+
+#[test]
+fn test_kuzmin_disk() {
+    let bodies = utils::generate_kuzmin_disk(&mut utils::seeded_rng(18), 10000, 1.0, 1.0, 0.0);
+
+    // Half the (untruncated) mass lies inside a √3
+    let half = bodies.iter().filter(|b| b.position[0].hypot(b.position[1]) < 3f32.sqrt()).count() as f32;
+    assert!((half / 10000.0 - 0.5 / 0.99).abs() < 0.02, "fraction inside: {}", half / 10000.0);
+
+    for b in bodies.iter().take(100) {
+        let r = b.position[0].hypot(b.position[1]);
+        let v_phi = (b.position[0] * b.velocity[1] - b.position[1] * b.velocity[0]) / r;
+        let expected = (r * r / (r * r + 1.0).powf(1.5)).sqrt();
+        assert!((v_phi - expected).abs() < 1e-3, "v_phi {} vs {}", v_phi, expected);
+    }
+}
+
+// ========== Zel'dovich ==========
+
+#[test]
+fn test_zeldovich_without_power_is_a_lattice() {
+    let bodies = utils::generate_zeldovich(&mut utils::seeded_rng(19), 8, 4.0, 2.0, |_| 0.0, 1.0);
+    assert_eq!(bodies.len(), 64);
+    assert_eq!(bodies[0].position, [-1.75, -1.75]);
+    assert_eq!(bodies[63].position, [1.75, 1.75]);
+    assert!(bodies.iter().all(|b| b.velocity == [0.0, 0.0]));
+    assert_relative_eq!(diagnostics::total_mass(&bodies), 2.0, max_relative = 1e-6);
+}
+
+#[test]
+fn test_zeldovich_displacement_variance() {
+    // With P(k) = A k², every mode contributes A / L² to <|ψ|²>
+    let (n, box_size, amplitude) = (64usize, 10.0f64, 1e-4f64);
+    let bodies = utils::generate_zeldovich(&mut utils::seeded_rng(20), n, box_size, 1.0, |k| amplitude * k * k, 2.0);
+
+    let half = (box_size / 2.0) as f32;
+    assert!(bodies.iter().all(|b| b.position.iter().all(|&x| (-half..half).contains(&x))));
+
+    // Velocities are 2ψ, and the mean displacement vanishes with the k = 0 mode
+    let modes = (n * n - (2 * n - 1)) as f64;
+    let expected = amplitude * modes / (box_size * box_size);
+    let psi2 = bodies.iter().map(|b| (b.velocity[0] as f64).powi(2) + (b.velocity[1] as f64).powi(2)).sum::<f64>()
+        / (4.0 * (n * n) as f64);
+    assert!((psi2 / expected - 1.0).abs() < 0.05, "<|ψ|²> = {} vs {}", psi2, expected);
+    let momentum = diagnostics::linear_momentum(&bodies);
+    assert!(momentum[0].abs() < 1e-6 && momentum[1].abs() < 1e-6);
+
+    // Displacement from the cell centre matches the velocity
+    let cell = box_size as f32 / n as f32;
+    for (index, b) in bodies.iter().enumerate().take(50) {
+        let q = [(index % n) as f32 + 0.5, (index / n) as f32 + 0.5].map(|c| c * cell - half);
+        for (k, q) in q.iter().enumerate() {
+            assert!((b.position[k] - q - 0.5 * b.velocity[k]).abs() < 1e-4);
+        }
+    }
+}